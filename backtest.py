@@ -0,0 +1,188 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+import datetime
+
+import numpy as np
+import pandas as pd
+
+import performance
+import rpar
+
+
+def _is_rebalancing_day(schedule, drift_threshold, previous_date, date,
+                        current_weights, target_weights):
+
+    if schedule == 'weekly':
+        return date.isocalendar()[1] != previous_date.isocalendar()[1]
+    elif schedule == 'monthly':
+        return date.month != previous_date.month
+    elif schedule == 'quarterly':
+        return date.quarter != previous_date.quarter
+    elif schedule == 'drift':
+        return np.max(np.abs(current_weights - target_weights)) > drift_threshold
+    else:
+        raise ValueError('unknown rebalancing schedule {}'.format(schedule))
+
+
+def _load_with_lookback(yahoo_tickers, start_date, end_date, lookback_days):
+
+    # History before the first day is loaded to calculate its weights
+    prices = rpar.get_prices(yahoo_tickers,
+                             start_date - datetime.timedelta(days=lookback_days),
+                             end_date)
+    missing_tickers = rpar.find_tickers_with_missing_data(prices)
+    if missing_tickers:
+        raise ValueError('missing data for tickers {}'.format(
+            ', '.join(missing_tickers)))
+
+    return prices, prices.loc[pd.Timestamp(start_date):]
+
+
+def _anchored_options(weights_options, held_weights):
+
+    # Weights held before recalculation are the previous weights turnover
+    # limits anchor to, before anything is held the limits can't apply
+    if held_weights is None:
+        return {key: value for key, value in weights_options.items()
+                if key not in ('turnover_penalty', 'max_turnover')}
+    return dict(weights_options, previous_weights=held_weights)
+
+
+def _weights_with_convergence(prices, weights_options):
+
+    # Closed form methods have no diagnostics and always count as converged
+    weights, diagnostics, _ = rpar.get_weights_with_diagnostics(
+        prices, **weights_options)
+    return weights.values, diagnostics is None or diagnostics['converged']
+
+
+def get_weights_history(yahoo_tickers, start_date, end_date, window_days,
+                        frequency, **weights_options):
+
+    # Weights options are keyword arguments of rpar.get_weights
+    prices, period = _load_with_lookback(yahoo_tickers, start_date, end_date,
+                                         window_days)
+    if period.empty:
+        raise ValueError('no prices in the window')
+
+    dates = []
+    weights = []
+    risk_contributions = []
+    converged = []
+    previous_date = None
+    for date in period.index:
+        if previous_date is None or _is_rebalancing_day(
+                frequency, None, previous_date, date, None, None):
+            window = prices.loc[date - pd.Timedelta(days=window_days):date]
+            if len(window) < 2:
+                raise ValueError('not enough history to calculate weights '
+                                 'on {}'.format(date.strftime('%Y-%m-%d')))
+            date_weights, date_converged = _weights_with_convergence(
+                window, _anchored_options(
+                    weights_options, weights[-1] if weights else None))
+            dates.append(date.strftime('%Y-%m-%d'))
+            weights.append(date_weights.tolist())
+            converged.append(date_converged)
+            risk_contributions.append(
+                rpar.get_risk_contributions(window, date_weights))
+        previous_date = date
+
+    return {'tickers': list(yahoo_tickers),
+            'method': weights_options.get('method', 'risk_parity'),
+            'dates': dates,
+            'weights': weights,
+            'risk_contributions': risk_contributions,
+            'converged': converged}
+
+
+def run_backtest(yahoo_tickers, start_date, end_date, lookback_days, schedule,
+                 drift_threshold=None, initial_value=1.0, risk_free_rate=0.0,
+                 cost_model=None, benchmarks=None, **weights_options):
+
+    # Weights options are keyword arguments of rpar.get_weights
+    prices, period = _load_with_lookback(yahoo_tickers, start_date, end_date,
+                                         lookback_days)
+    if len(period) < 2:
+        raise ValueError('no prices in the backtest period')
+
+    # Whatever isn't invested is held as cash earning the risk free rate,
+    # negative cash is borrowed at it
+    quantities = None
+    cash = initial_value
+    target_weights = None
+    values = []
+    rebalances = []
+    total_turnover = 0.0
+    total_cost = 0.0
+    previous_date = None
+    for date, day_prices in period.iterrows():
+        day_prices = day_prices.values
+        if previous_date is not None:
+            cash *= (1 + risk_free_rate) ** (
+                (date - previous_date).days / 365.0)
+        value = cash + (0.0 if quantities is None
+                        else float(np.sum(quantities * day_prices)))
+        current_weights = (None if quantities is None
+                           else quantities * day_prices / value)
+
+        if quantities is None or _is_rebalancing_day(
+                schedule, drift_threshold, previous_date, date,
+                current_weights, target_weights):
+            # Only prices known before the day are used, trading happens at
+            # its close
+            history = prices.loc[
+                date - pd.Timedelta(days=lookback_days):date].iloc[:-1]
+            if len(history) < 2:
+                raise ValueError('not enough history to calculate weights '
+                                 'on {}'.format(date.strftime('%Y-%m-%d')))
+            target_weights, converged = _weights_with_convergence(
+                history, _anchored_options(weights_options, current_weights))
+
+            # Costs are paid from the portfolio, a few iterations settle the
+            # value left to invest after them
+            held_quantities = (np.zeros(len(day_prices)) if quantities is None
+                               else quantities)
+            cost = 0.0
+            for _ in range(5):
+                new_quantities = (value - cost) * target_weights / day_prices
+                cost = float(rpar.trade_costs(
+                    pd.Series((new_quantities - held_quantities) * day_prices,
+                              index=period.columns),
+                    cost_model).sum())
+            if cost >= value:
+                raise ValueError('trading costs exceed the portfolio value '
+                                 'on {}'.format(date.strftime('%Y-%m-%d')))
+            new_quantities = (value - cost) * target_weights / day_prices
+
+            turnover = (0.0 if quantities is None else float(
+                np.sum(np.abs(new_quantities - quantities) * day_prices) /
+                value))
+            total_turnover += turnover
+            total_cost += cost
+            value -= cost
+            quantities = new_quantities
+            cash = value - float(np.sum(quantities * day_prices))
+            rebalances.append({'date': date.strftime('%Y-%m-%d'),
+                               'weights': target_weights.tolist(),
+                               'turnover': turnover,
+                               'cost': cost,
+                               'converged': converged})
+
+        values.append(value)
+        previous_date = date
+
+    values = pd.Series(values, index=period.index)
+    stats = performance.performance_stats(values, risk_free_rate)
+    years = (values.index[-1] - values.index[0]).days / 365.25
+
+    return {'tickers': list(yahoo_tickers),
+            'method': weights_options.get('method', 'risk_parity'),
+            'dates': values.index.strftime('%Y-%m-%d').tolist(),
+            'values': values.tolist(),
+            'rebalances': rebalances,
+            'turnover': total_turnover / years,
+            'total_cost': total_cost,
+            'stats': stats,
+            'benchmarks': performance.compare_with_benchmarks(
+                values, benchmarks or [], risk_free_rate)}
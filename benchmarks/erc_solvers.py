@@ -0,0 +1,71 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Solve time of the risk parity solvers against universe size on random factor
+# model covariances, run it from the project root:
+#
+#   python benchmarks/erc_solvers.py --sizes 10 50 100 250 500
+
+import argparse
+import os
+import sys
+import time
+
+import numpy as np
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import rpar  # noqa: E402
+
+
+def random_covariances(size, rng, factors=5):
+
+    # A few common factors plus specific risk, like a universe of stocks
+    exposures = rng.normal(size=(size, factors))
+    factor_variances = rng.uniform(0.01, 0.04, factors)
+    specific_variances = rng.uniform(0.01, 0.09, size)
+    return (exposures @ np.diag(factor_variances) @ exposures.T +
+            np.diag(specific_variances))
+
+
+def main():
+
+    parser = argparse.ArgumentParser(
+        description='Solve time of risk parity solvers by universe size')
+    parser.add_argument('--sizes', type=int, nargs='+',
+                        default=[10, 25, 50, 100, 250, 500])
+    parser.add_argument('--solvers', nargs='+',
+                        default=list(rpar.RISK_PARITY_SOLVERS))
+    parser.add_argument('--slsqp-max-size', type=int, default=100,
+                        help='larger universes take SLSQP minutes')
+    parser.add_argument('--repeats', type=int, default=3)
+    parser.add_argument('--seed', type=int, default=0)
+    args = parser.parse_args()
+
+    rng = np.random.default_rng(args.seed)
+    print('{:>6}  {:<20}{:>10}{:>12}{:>15}'.format(
+        'size', 'solver', 'seconds', 'iterations', 'max_deviation'))
+    for size in args.sizes:
+        covariances = random_covariances(size, rng)
+        budget = np.full(size, 1 / size)
+        for solver in args.solvers:
+            if solver == 'slsqp' and size > args.slsqp_max_size:
+                continue
+
+            # Best of a few runs to smooth out noise
+            seconds = None
+            for _ in range(args.repeats):
+                started = time.perf_counter()
+                result = rpar.RISK_PARITY_SOLVERS[solver](covariances, budget)
+                elapsed = time.perf_counter() - started
+                seconds = elapsed if seconds is None else min(seconds, elapsed)
+
+            max_deviation = np.max(np.abs(
+                rpar._risk_contribution_shares(result.x, covariances) - budget))
+            print('{:>6}  {:<20}{:>10.4f}{:>12}{:>15.2e}{}'.format(
+                size, solver, seconds, result.nit, max_deviation,
+                '' if result.success else '  not converged'))
+
+
+if __name__ == '__main__':
+    main()
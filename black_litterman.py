@@ -0,0 +1,62 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+import numpy as np
+
+import rpar
+
+# Risk aversion and uncertainty of equilibrium returns relative to
+# covariances unless given
+RISK_AVERSION = 2.5
+TAU = 0.05
+
+
+def _view_matrices(tickers, views):
+
+    # One row per view picking its ticker, less the one it is relative to
+    picks = np.zeros((len(views), len(tickers)))
+    for row, (ticker, relative_to, _, _) in enumerate(views):
+        picks[row, tickers.index(ticker)] += 1.0
+        if relative_to is not None:
+            picks[row, tickers.index(relative_to)] -= 1.0
+    expected_returns = np.array([view[2] for view in views], dtype=float)
+    confidences = np.array([view[3] for view in views], dtype=float)
+    return picks, expected_returns, confidences
+
+
+def black_litterman(prices, weights, views, risk_aversion=RISK_AVERSION,
+                    tau=TAU):
+
+    # Views are (ticker, relative_to or None, expected_return, confidence)
+    tickers = list(prices.columns)
+    covariances = rpar.get_covariances(prices)
+    weights = np.asarray(weights, dtype=float)
+
+    # Returns for which the weights are the mean-variance optimum
+    equilibrium_returns = risk_aversion * covariances @ weights
+
+    posterior_returns = equilibrium_returns
+    if views:
+        picks, expected_returns, confidences = _view_matrices(tickers, views)
+        prior_covariances = tau * covariances
+
+        # View uncertainty proportional to the prior variance of the view,
+        # none for full confidence and growing as confidence drops
+        view_variances = np.diag(picks @ prior_covariances @ picks.T)
+        view_covariances = np.diag(
+            (1 - confidences) / confidences * view_variances)
+        try:
+            adjustment = np.linalg.solve(
+                picks @ prior_covariances @ picks.T + view_covariances,
+                expected_returns - picks @ equilibrium_returns)
+        except np.linalg.LinAlgError:
+            raise ValueError('views are redundant, some of them are '
+                             'combinations of others held with full '
+                             'confidence')
+        posterior_returns = (equilibrium_returns +
+                             prior_covariances @ picks.T @ adjustment)
+
+    return {'equilibrium_returns': equilibrium_returns.tolist(),
+            'posterior_returns': posterior_returns.tolist(),
+            'mean_variance_weights': np.linalg.solve(
+                risk_aversion * covariances, posterior_returns).tolist()}
@@ -0,0 +1,206 @@
+mod output;
+
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use core::{
+  PricesQuery, PricesResponse, SearchQuery, TickerInfo, WeightsMethod, WeightsRequest,
+  WeightsResponse,
+};
+use output::{Format, Table};
+use pyo3::prelude::*;
+use service::{py_bridge, search::find_tickers};
+use structopt::StructOpt;
+
+/// Risk balanced portfolio from the terminal.
+///
+/// Calculates locally unless a service is given, which requires python modules of the service
+/// in the current work dir.
+#[derive(StructOpt)]
+#[structopt(name = "rbp")]
+struct Opt {
+  /// Service API root to call, e.g. http://localhost:9090/service/v1
+  #[structopt(long, global = true, env = "RBP_SERVICE")]
+  service: Option<String>,
+  /// Output format: table, json or csv
+  #[structopt(long, global = true, default_value = "table")]
+  format: Format,
+  #[structopt(subcommand)]
+  command: Command,
+}
+
+#[derive(StructOpt)]
+enum Command {
+  /// Calculates portfolio weights of tickers
+  Weights {
+    #[structopt(required = true)]
+    tickers: Vec<String>,
+    #[structopt(flatten)]
+    window: Window,
+    /// Weighting scheme: risk_parity, inverse_volatility, equal_weight or
+    /// expected_shortfall_risk_parity
+    #[structopt(long, default_value = "risk_parity")]
+    method: WeightsMethod,
+    /// Annualized portfolio volatility to scale weights to, e.g. 0.1
+    #[structopt(long)]
+    target_volatility: Option<f64>,
+    /// Cap on gross exposure when scaling up, 1 by default
+    #[structopt(long)]
+    max_leverage: Option<f64>,
+  },
+  /// Searches tickers matching a term
+  Search { term: String },
+  /// Loads adjusted close prices of tickers
+  Prices {
+    #[structopt(required = true)]
+    tickers: Vec<String>,
+    #[structopt(flatten)]
+    window: Window,
+  },
+}
+
+#[derive(StructOpt)]
+struct Window {
+  /// First day of the price window (YYYY-MM-DD), a year before end date by default
+  #[structopt(long)]
+  start_date: Option<NaiveDate>,
+  /// Last day of the price window (YYYY-MM-DD), today by default
+  #[structopt(long)]
+  end_date: Option<NaiveDate>,
+}
+
+enum Backend {
+  Local,
+  Remote(client::Client),
+}
+
+impl Backend {
+  async fn weights(&self, request: WeightsRequest) -> anyhow::Result<WeightsResponse> {
+    match self {
+      Backend::Local => local(|py| py_bridge::calc_weights(py, &request)),
+      Backend::Remote(client) => Ok(client.post_weights(&request).await?),
+    }
+  }
+
+  async fn search(&self, query: SearchQuery) -> anyhow::Result<Vec<TickerInfo>> {
+    match self {
+      Backend::Local => {
+        let found: Option<Vec<TickerInfo>> = serde_json::from_value(find_tickers(&query).await?)?;
+        Ok(found.unwrap_or_default())
+      }
+      Backend::Remote(client) => Ok(client.search(&query).await?),
+    }
+  }
+
+  async fn prices(&self, query: PricesQuery) -> anyhow::Result<PricesResponse> {
+    match self {
+      Backend::Local => local(|py| py_bridge::load_prices_table(py, &query)),
+      Backend::Remote(client) => Ok(client.prices(&query).await?),
+    }
+  }
+}
+
+fn local<T, F>(call: F) -> anyhow::Result<T>
+where
+  F: FnOnce(Python) -> PyResult<anyhow::Result<T>>,
+{
+  Python::with_gil(|py| call(py).map_err(|e| anyhow!("python error: {}", e))?)
+}
+
+#[actix_rt::main]
+async fn main() -> anyhow::Result<()> {
+  let opt = Opt::from_args();
+  let backend = match opt.service {
+    Some(base) => Backend::Remote(client::Client::new(base)),
+    None => Backend::Local,
+  };
+
+  match opt.command {
+    Command::Weights {
+      tickers,
+      window,
+      method,
+      target_volatility,
+      max_leverage,
+    } => {
+      let response = backend
+        .weights(WeightsRequest {
+          tickers,
+          start_date: window.start_date,
+          end_date: window.end_date,
+          method: Some(method),
+          target_volatility,
+          max_leverage,
+          ..Default::default()
+        })
+        .await?;
+      if let Some(solver) = response.solver.as_ref().filter(|solver| !solver.converged) {
+        eprintln!(
+          "warning: risk parity solver didn't converge ({}), risk contributions deviate from the budget by up to {:.2e}",
+          solver.status, solver.max_deviation
+        );
+      }
+      let table = Table {
+        headers: ["ticker", "weight", "risk_contribution"]
+          .iter()
+          .map(|header| header.to_string())
+          .collect(),
+        rows: (0..response.tickers.len())
+          .map(|i| {
+            vec![
+              response.tickers[i].clone(),
+              format!("{:.6}", response.weights[i]),
+              format!("{:.6}", response.risk_contributions[i]),
+            ]
+          })
+          .collect(),
+      };
+      output::print(opt.format, &response, table)
+    }
+    Command::Search { term } => {
+      let found = backend.search(SearchQuery { term }).await?;
+      let table = Table {
+        headers: ["symbol", "name", "exchange", "type"]
+          .iter()
+          .map(|header| header.to_string())
+          .collect(),
+        rows: found
+          .iter()
+          .map(|info| {
+            vec![
+              info.symbol.clone(),
+              info.name.clone(),
+              info.exch_disp.clone(),
+              info.type_disp.clone(),
+            ]
+          })
+          .collect(),
+      };
+      output::print(opt.format, &found, table)
+    }
+    Command::Prices { tickers, window } => {
+      let response = backend
+        .prices(PricesQuery {
+          tickers,
+          start_date: window.start_date,
+          end_date: window.end_date,
+        })
+        .await?;
+      let table = Table {
+        headers: std::iter::once("date".to_string())
+          .chain(response.tickers.iter().cloned())
+          .collect(),
+        rows: response
+          .dates
+          .iter()
+          .zip(response.prices.iter())
+          .map(|(date, prices)| {
+            std::iter::once(date.to_string())
+              .chain(prices.iter().map(|price| format!("{:.4}", price)))
+              .collect()
+          })
+          .collect(),
+      };
+      output::print(opt.format, &response, table)
+    }
+  }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::io::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+  Table,
+  Json,
+  Csv,
+}
+
+impl FromStr for Format {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "table" => Ok(Format::Table),
+      "json" => Ok(Format::Json),
+      "csv" => Ok(Format::Csv),
+      _ => Err(format!("unknown output format {}", s)),
+    }
+  }
+}
+
+/// Tabular view of a response used by table and csv formats
+pub struct Table {
+  pub headers: Vec<String>,
+  pub rows: Vec<Vec<String>>,
+}
+
+/// Prints response as json or its tabular view
+pub fn print<T: Serialize>(format: Format, response: &T, table: Table) -> anyhow::Result<()> {
+  let stdout = std::io::stdout();
+  write(stdout.lock(), format, response, table)
+}
+
+fn write<W: Write, T: Serialize>(
+  mut out: W,
+  format: Format,
+  response: &T,
+  table: Table,
+) -> anyhow::Result<()> {
+  match format {
+    Format::Json => {
+      serde_json::to_writer_pretty(&mut out, response)?;
+      writeln!(out)?;
+    }
+    Format::Csv => {
+      let mut writer = csv::Writer::from_writer(out);
+      writer.write_record(&table.headers)?;
+      for row in table.rows.iter() {
+        writer.write_record(row)?;
+      }
+      writer.flush()?;
+    }
+    Format::Table => {
+      let mut widths: Vec<usize> = table.headers.iter().map(String::len).collect();
+      for row in table.rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+          *width = (*width).max(cell.chars().count());
+        }
+      }
+      for row in std::iter::once(&table.headers).chain(table.rows.iter()) {
+        let line: Vec<String> = row
+          .iter()
+          .zip(widths.iter())
+          .map(|(cell, width)| format!("{:width$}", cell, width = width))
+          .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+      }
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn written(format: Format) -> String {
+    let table = Table {
+      headers: vec!["ticker".to_string(), "weight".to_string()],
+      rows: vec![
+        vec!["SPY".to_string(), "0.4".to_string()],
+        vec!["TLT".to_string(), "0.6".to_string()],
+      ],
+    };
+    let mut out = Vec::new();
+    write(&mut out, format, &serde_json::json!({ "SPY": 0.4 }), table).unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn it_writes_all_formats() {
+    assert_eq!(written(Format::Json), "{\n  \"SPY\": 0.4\n}\n");
+    assert_eq!(written(Format::Csv), "ticker,weight\nSPY,0.4\nTLT,0.6\n");
+    assert_eq!(
+      written(Format::Table),
+      "ticker  weight\nSPY     0.4\nTLT     0.6\n"
+    );
+  }
+
+  #[test]
+  fn it_parses_formats() {
+    assert!(matches!("csv".parse(), Ok(Format::Csv)));
+    assert!("yaml".parse::<Format>().is_err());
+  }
+}
@@ -0,0 +1,200 @@
+use core::{
+  AllocationRequest, AllocationResponse, BacktestRequest, BacktestResponse, BlackLittermanRequest,
+  BlackLittermanResponse, CovarianceQuery, CovarianceResponse, ExportWeightsQuery, FrontierRequest,
+  FrontierResponse, GetWeightsQuery, PricesQuery, PricesResponse, RebalanceRequest,
+  RebalanceResponse, RiskRequest, RiskResponse, SearchQuery, SimulationRequest, SimulationResponse,
+  StressTestRequest, StressTestResponse, TickerInfo, WeightsHistoryRequest, WeightsHistoryResponse,
+  WeightsRequest, WeightsResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(Debug)]
+pub enum Error {
+  Http(reqwest::Error),
+  Query(serde_qs::Error),
+  Service {
+    status: reqwest::StatusCode,
+    message: String,
+  },
+}
+
+impl std::fmt::Display for Error {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Error::Http(err) => write!(f, "error sending request: {}", err),
+      Error::Query(err) => write!(f, "error encoding query: {}", err),
+      Error::Service { status, message } => write!(f, "{}: {}", status, message),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Http(err) => Some(err),
+      Error::Query(err) => Some(err),
+      Error::Service { .. } => None,
+    }
+  }
+}
+
+impl From<reqwest::Error> for Error {
+  fn from(err: reqwest::Error) -> Error {
+    Error::Http(err)
+  }
+}
+
+impl From<serde_qs::Error> for Error {
+  fn from(err: serde_qs::Error) -> Error {
+    Error::Query(err)
+  }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Client of the RBP service API
+#[derive(Clone, Debug)]
+pub struct Client {
+  base: String,
+  http: reqwest::Client,
+}
+
+impl Client {
+  /// Creates a client for the API root, e.g. `http://localhost:9090/service/v1`
+  pub fn new(base: impl Into<String>) -> Self {
+    Self {
+      base: base.into().trim_end_matches('/').to_string(),
+      http: reqwest::Client::new(),
+    }
+  }
+
+  pub async fn get_weights(&self, query: &GetWeightsQuery) -> Result<Vec<f64>> {
+    self.get("weights", query).await
+  }
+
+  pub async fn post_weights(&self, request: &WeightsRequest) -> Result<WeightsResponse> {
+    self.post("weights", request).await
+  }
+
+  /// Content of the weights file in the requested format
+  pub async fn export_weights(&self, query: &ExportWeightsQuery) -> Result<Vec<u8>> {
+    let response = self
+      .http
+      .get(&self.query_url("weights/export", query)?)
+      .send()
+      .await?;
+    Ok(Self::check(response).await?.bytes().await?.to_vec())
+  }
+
+  pub async fn weights_history(
+    &self,
+    request: &WeightsHistoryRequest,
+  ) -> Result<WeightsHistoryResponse> {
+    self.post("weights/history", request).await
+  }
+
+  pub async fn allocate(&self, request: &AllocationRequest) -> Result<AllocationResponse> {
+    self.post("allocation", request).await
+  }
+
+  pub async fn rebalance(&self, request: &RebalanceRequest) -> Result<RebalanceResponse> {
+    self.post("rebalance", request).await
+  }
+
+  pub async fn backtest(&self, request: &BacktestRequest) -> Result<BacktestResponse> {
+    self.post("backtest", request).await
+  }
+
+  pub async fn risk(&self, request: &RiskRequest) -> Result<RiskResponse> {
+    self.post("risk", request).await
+  }
+
+  pub async fn covariance(&self, query: &CovarianceQuery) -> Result<CovarianceResponse> {
+    self.get("covariance", query).await
+  }
+
+  pub async fn black_litterman(
+    &self,
+    request: &BlackLittermanRequest,
+  ) -> Result<BlackLittermanResponse> {
+    self.post("black-litterman", request).await
+  }
+
+  pub async fn frontier(&self, request: &FrontierRequest) -> Result<FrontierResponse> {
+    self.post("frontier", request).await
+  }
+
+  pub async fn simulate(&self, request: &SimulationRequest) -> Result<SimulationResponse> {
+    self.post("simulation", request).await
+  }
+
+  pub async fn stress_test(&self, request: &StressTestRequest) -> Result<StressTestResponse> {
+    self.post("stress", request).await
+  }
+
+  pub async fn prices(&self, query: &PricesQuery) -> Result<PricesResponse> {
+    self.get("prices", query).await
+  }
+
+  pub async fn search(&self, query: &SearchQuery) -> Result<Vec<TickerInfo>> {
+    // service responds with null when nothing was found
+    let found: Option<Vec<TickerInfo>> = self.get("search", query).await?;
+    Ok(found.unwrap_or_default())
+  }
+
+  fn url(&self, sub_path: &str) -> String {
+    format!("{}/{}", self.base, sub_path)
+  }
+
+  fn query_url<Query: Serialize>(&self, sub_path: &str, query: &Query) -> Result<String> {
+    Ok(format!(
+      "{}?{}",
+      self.url(sub_path),
+      serde_qs::to_string(query)?
+    ))
+  }
+
+  async fn get<Query, Out>(&self, sub_path: &str, query: &Query) -> Result<Out>
+  where
+    Query: Serialize,
+    Out: DeserializeOwned,
+  {
+    let response = self
+      .http
+      .get(&self.query_url(sub_path, query)?)
+      .send()
+      .await?;
+    Self::parse(response).await
+  }
+
+  async fn post<In, Out>(&self, sub_path: &str, payload: &In) -> Result<Out>
+  where
+    In: Serialize,
+    Out: DeserializeOwned,
+  {
+    let response = self
+      .http
+      .post(&self.url(sub_path))
+      .json(payload)
+      .send()
+      .await?;
+    Self::parse(response).await
+  }
+
+  async fn parse<Out: DeserializeOwned>(response: reqwest::Response) -> Result<Out> {
+    Ok(Self::check(response).await?.json().await?)
+  }
+
+  /// Turns unsuccessful responses into errors with the body as message
+  async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+      Ok(response)
+    } else {
+      Err(Error::Service {
+        status,
+        message: response.text().await?,
+      })
+    }
+  }
+}
@@ -1,11 +1,991 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
 #[cfg_attr(feature = "client", derive(serde::Serialize))]
 #[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct GetWeightsQuery {
   pub tickers: Vec<String>,
 }
 
+/// JSON body of `POST /service/v1/weights`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct WeightsRequest {
+  pub tickers: Vec<String>,
+  /// First day of the price window, a year before `end_date` if missing
+  pub start_date: Option<NaiveDate>,
+  /// Last day of the price window, today if missing
+  pub end_date: Option<NaiveDate>,
+  /// Desired share of portfolio risk per ticker, equal if missing
+  pub risk_budget: Option<Vec<f64>>,
+  /// Risk budgets of groups of tickers such as asset classes or sectors, every ticker belongs to
+  /// exactly one group, can't be combined with `risk_budget`
+  pub risk_budget_groups: Option<Vec<RiskBudgetGroup>>,
+  /// Weighting scheme, risk parity if missing
+  pub method: Option<WeightsMethod>,
+  /// Annualized portfolio volatility weights are scaled to, unscaled if missing
+  pub target_volatility: Option<f64>,
+  /// Cap on gross exposure when scaling up to the target volatility, the long-short gross exposure
+  /// or else 1 if missing
+  pub max_leverage: Option<f64>,
+  /// Largest deviation of risk contributions from the budget for risk parity weights to count as
+  /// converged, 1e-4 if missing
+  pub tolerance: Option<f64>,
+  /// Fails instead of flagging risk parity weights which didn't converge
+  pub fail_unconverged: Option<bool>,
+  /// Algorithm risk parity weights are solved with, Newton if missing
+  pub solver: Option<RiskParitySolver>,
+  /// Currently held weights in the order of tickers, the solver starts from them
+  pub previous_weights: Option<Vec<f64>>,
+  /// Cost per unit of turnover from previous weights, weighed against the squared deviation of
+  /// risk contribution shares from the budget
+  pub turnover_penalty: Option<f64>,
+  /// Largest allowed sum of absolute weight changes from previous weights
+  pub max_turnover: Option<f64>,
+  /// Holds some tickers short, long only if missing
+  pub long_short: Option<LongShort>,
+  /// How expected shortfall risk parity estimates the shortfall, historical if missing
+  pub shortfall_estimator: Option<ShortfallEstimator>,
+  /// Confidence level of expected shortfall, 0.95 if missing
+  pub shortfall_confidence: Option<f64>,
+}
+
+/// Long-short weights, the absolute risk contributions of long and short positions are balanced
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct LongShort {
+  /// Side of each ticker in the order of tickers
+  pub sides: Option<Vec<PositionSide>>,
+  /// Expected return of each ticker, positive ones are held long and negative ones short, used
+  /// when sides are missing
+  pub views: Option<Vec<f64>>,
+  /// Sum of absolute weights before target volatility scaling, 1 if missing
+  pub gross_exposure: Option<f64>,
+  /// Largest absolute sum of weights, risk parity gives up some balance to stay within it
+  pub max_net_exposure: Option<f64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PositionSide {
+  #[default]
+  Long,
+  Short,
+}
+
+impl PositionSide {
+  pub fn sign(&self) -> f64 {
+    match self {
+      PositionSide::Long => 1.0,
+      PositionSide::Short => -1.0,
+    }
+  }
+}
+
+/// Share of portfolio risk given to a group of tickers, subdivided among them
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct RiskBudgetGroup {
+  pub name: String,
+  pub tickers: Vec<String>,
+  /// Positive share of portfolio risk of the group, budgets of all groups are scaled to sum up to 1
+  pub budget: f64,
+  /// Positive split of the group budget among its tickers in their order, equal if missing
+  pub sub_budget: Option<Vec<f64>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WeightsMethod {
+  /// Equal risk contribution (or the requested risk budget) via covariance matrix
+  #[default]
+  RiskParity,
+  /// Weights proportional to inverse volatility, ignores correlations
+  InverseVolatility,
+  EqualWeight,
+  /// Equal contribution (or the requested risk budget) to the expected shortfall of daily returns
+  /// instead of their volatility, only losses count as risk
+  ExpectedShortfallRiskParity,
+}
+
+impl WeightsMethod {
+  pub const ALL: [WeightsMethod; 4] = [
+    WeightsMethod::RiskParity,
+    WeightsMethod::InverseVolatility,
+    WeightsMethod::EqualWeight,
+    WeightsMethod::ExpectedShortfallRiskParity,
+  ];
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      WeightsMethod::RiskParity => "risk_parity",
+      WeightsMethod::InverseVolatility => "inverse_volatility",
+      WeightsMethod::EqualWeight => "equal_weight",
+      WeightsMethod::ExpectedShortfallRiskParity => "expected_shortfall_risk_parity",
+    }
+  }
+}
+
+impl fmt::Display for WeightsMethod {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl FromStr for WeightsMethod {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    WeightsMethod::ALL
+      .iter()
+      .find(|method| method.as_str() == s)
+      .copied()
+      .ok_or_else(|| format!("unknown weights method {}", s))
+  }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ShortfallEstimator {
+  /// Average of the worst daily returns beyond the confidence level
+  #[default]
+  Historical,
+  /// Normal tail adjusted for skew and kurtosis, smoother on short windows
+  CornishFisher,
+}
+
+impl ShortfallEstimator {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ShortfallEstimator::Historical => "historical",
+      ShortfallEstimator::CornishFisher => "cornish_fisher",
+    }
+  }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RiskParitySolver {
+  /// Newton steps on Spinu's convex formulation, fast and reliable for hundreds of tickers
+  #[default]
+  Newton,
+  /// Cyclical coordinate descent on the same formulation, cheap steps for very large universes
+  CoordinateDescent,
+  /// Generic constrained least squares, slow beyond a few dozen tickers
+  Slsqp,
+}
+
+impl RiskParitySolver {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      RiskParitySolver::Newton => "newton",
+      RiskParitySolver::CoordinateDescent => "coordinate_descent",
+      RiskParitySolver::Slsqp => "slsqp",
+    }
+  }
+}
+
+/// The plain list of weights has no room for solver diagnostics, so unconverged weights fail
+impl From<GetWeightsQuery> for WeightsRequest {
+  fn from(query: GetWeightsQuery) -> Self {
+    WeightsRequest {
+      tickers: query.tickers,
+      fail_unconverged: Some(true),
+      ..Default::default()
+    }
+  }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct WeightsResponse {
+  pub tickers: Vec<String>,
+  pub weights: Vec<f64>,
+  /// Share of portfolio volatility contributed by each ticker, sums up to 1
+  pub risk_contributions: Vec<f64>,
+  pub method: WeightsMethod,
+  /// First day with prices in the window
+  pub start_date: NaiveDate,
+  /// Last day with prices in the window
+  pub end_date: NaiveDate,
+  /// Annualized volatility of the weighted portfolio over the window
+  pub volatility: f64,
+  /// Sum of absolute weights, the rest up to 1 is cash and the excess over 1 is borrowed
+  pub gross_exposure: f64,
+  /// Outcome of the risk parity solver, missing for closed form methods
+  pub solver: Option<SolverDiagnostics>,
+  /// Present when the estimated covariance matrix wasn't positive definite and got repaired
+  pub covariance_repair: Option<CovarianceRepair>,
+  /// Sum of absolute weight changes from previous weights, both scaled to a unit gross exposure
+  /// like the solver's turnover limits, when previous weights are given
+  pub turnover: Option<f64>,
+}
+
+/// Eigenvalue clipping which made an estimated covariance matrix positive definite
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct CovarianceRepair {
+  /// Smallest eigenvalue of the estimated correlation matrix
+  pub min_eigenvalue: f64,
+  /// Number of eigenvalues raised to the floor, variances are kept as estimated
+  pub clipped_eigenvalues: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct SolverDiagnostics {
+  /// Always SLSQP for expected shortfall and when turnover or net exposure is limited
+  pub solver: RiskParitySolver,
+  /// The solver reported success and risk contributions are within tolerance of the budget,
+  /// unless turnover limits keep them away on purpose
+  pub converged: bool,
+  /// Solver exit message
+  pub status: String,
+  pub iterations: u32,
+  /// Final squared error between risk contributions and the budget
+  pub objective: f64,
+  /// Largest absolute difference between risk contribution shares and the budget, shares of
+  /// expected shortfall for that method
+  pub max_deviation: f64,
+  pub tolerance: f64,
+}
+
+/// JSON body of `POST /service/v1/weights/history`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct WeightsHistoryRequest {
+  /// Tickers and options, its window is the period weights are calculated over
+  pub weights: WeightsRequest,
+  /// Days of prices up to each date weights are calculated from, 365 if missing
+  pub window_days: Option<u32>,
+  /// Spacing of dates weights are calculated at, monthly if missing
+  pub step: Option<Frequency>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+  /// First trading day of each week
+  Weekly,
+  /// First trading day of each month
+  #[default]
+  Monthly,
+  /// First trading day of each quarter
+  Quarterly,
+}
+
+impl Frequency {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Frequency::Weekly => "weekly",
+      Frequency::Monthly => "monthly",
+      Frequency::Quarterly => "quarterly",
+    }
+  }
+}
+
+/// Rolling window weights, one row per date in the order of tickers
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct WeightsHistoryResponse {
+  pub tickers: Vec<String>,
+  pub method: WeightsMethod,
+  pub dates: Vec<NaiveDate>,
+  pub weights: Vec<Vec<f64>>,
+  pub risk_contributions: Vec<Vec<f64>>,
+  /// Per date, false when the risk parity solver didn't converge and the weights were kept anyway
+  pub converged: Vec<bool>,
+}
+
+/// Query of `GET /service/v1/weights/export`, a `WeightsRequest` plus file details
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone)]
+pub struct ExportWeightsQuery {
+  pub tickers: Vec<String>,
+  /// Human readable ticker names, in the same order as tickers
+  pub names: Option<Vec<String>>,
+  pub start_date: Option<NaiveDate>,
+  pub end_date: Option<NaiveDate>,
+  pub risk_budget: Option<Vec<f64>>,
+  pub method: Option<WeightsMethod>,
+  pub format: ExportFormat,
+}
+
+impl ExportWeightsQuery {
+  /// Exported files carry no solver diagnostics, so unconverged weights fail
+  pub fn weights_request(&self) -> WeightsRequest {
+    WeightsRequest {
+      tickers: self.tickers.clone(),
+      start_date: self.start_date,
+      end_date: self.end_date,
+      risk_budget: self.risk_budget.clone(),
+      method: self.method,
+      fail_unconverged: Some(true),
+      ..Default::default()
+    }
+  }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+  Csv,
+  Json,
+  Xlsx,
+}
+
+impl ExportFormat {
+  pub const ALL: [ExportFormat; 3] = [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Xlsx];
+
+  /// Also used as the file extension
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ExportFormat::Csv => "csv",
+      ExportFormat::Json => "json",
+      ExportFormat::Xlsx => "xlsx",
+    }
+  }
+}
+
+/// JSON body of `POST /service/v1/rebalance`
 #[cfg_attr(feature = "client", derive(serde::Serialize))]
 #[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct RebalanceRequest {
+  /// Target portfolio, its `end_date` is also the date of prices used for trading
+  pub weights: WeightsRequest,
+  /// Current quantity per ticker, tickers missing in the target portfolio are sold out
+  pub holdings: BTreeMap<String, f64>,
+  /// Cash available on top of holdings, zero if missing
+  pub cash: Option<f64>,
+  /// Minimal tradable quantity per ticker, 1 if missing
+  pub lot_sizes: Option<BTreeMap<String, f64>>,
+  /// Allows any quantity instead of whole lots
+  pub fractional: Option<bool>,
+  /// Trading costs paid from cash, none if missing
+  pub costs: Option<CostModel>,
+}
+
+/// Trading costs, each part is zero if missing
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct CostModel {
+  /// Fee per trade
+  pub fixed_fee: Option<f64>,
+  /// Commission in basis points of traded value
+  pub commission_bps: Option<f64>,
+  /// Spread and slippage in basis points of traded value per asset class
+  pub slippage_bps: Option<BTreeMap<String, f64>>,
+  /// Asset class per ticker, tickers without one have no slippage
+  pub asset_classes: Option<BTreeMap<String, String>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RebalanceResponse {
+  pub weights: WeightsResponse,
+  pub trades: Vec<Trade>,
+  /// Value of holdings and cash before trading
+  pub portfolio_value: f64,
+  /// Total value of buys and sells as a share of portfolio value
+  pub turnover: f64,
+  /// Sum of trade costs
+  pub total_cost: f64,
+  /// Cash left after trading and paying costs
+  pub remaining_cash: f64,
+  /// Annualized volatility of the difference between target and traded portfolios
+  pub tracking_error: f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Trade {
+  pub ticker: String,
+  /// Latest price trades are valued at
+  pub price: f64,
+  pub current_quantity: f64,
+  pub target_quantity: f64,
+  /// Positive to buy, negative to sell
+  pub quantity: f64,
+  /// Signed value of the trade, `quantity * price`
+  pub value: f64,
+  pub cost: f64,
+}
+
+/// JSON body of `POST /service/v1/allocation`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct AllocationRequest {
+  /// Target portfolio, prices at its `end_date` are used for allocation
+  pub weights: WeightsRequest,
+  /// Amount of money to invest
+  pub amount: f64,
+  /// Minimal tradable quantity per ticker, 1 if missing
+  pub lot_sizes: Option<BTreeMap<String, f64>>,
+  /// Allows any quantity instead of whole lots
+  pub fractional: Option<bool>,
+  /// Compared with the target weights held over the weights window
+  pub benchmarks: Option<Vec<Benchmark>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct AllocationResponse {
+  pub weights: WeightsResponse,
+  pub positions: Vec<Position>,
+  /// Value of all positions
+  pub invested: f64,
+  /// Part of the amount which couldn't be invested in whole lots
+  pub leftover_cash: f64,
+  /// Annualized volatility of the difference between target and realized portfolios
+  pub tracking_error: f64,
+  /// In the order of requested benchmarks
+  pub benchmarks: Vec<BenchmarkComparison>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Position {
+  pub ticker: String,
+  pub price: f64,
+  pub lot_size: f64,
+  pub quantity: f64,
+  pub value: f64,
+  pub target_weight: f64,
+  /// Share of the amount to invest, they sum up below 1 when there is leftover cash
+  pub realized_weight: f64,
+}
+
+/// JSON body of `POST /service/v1/backtest`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct BacktestRequest {
+  /// Portfolio to backtest, its window is the backtest period
+  pub weights: WeightsRequest,
+  /// Days of history before each rebalancing weights are calculated from, 365 if missing
+  pub lookback_days: Option<u32>,
+  /// When weights are recalculated and traded to, monthly if missing
+  pub rebalancing: Option<RebalanceSchedule>,
+  /// Portfolio value on the first day, 1 if missing
+  pub initial_value: Option<f64>,
+  /// Annual rate the Sharpe ratio is measured against, zero if missing
+  pub risk_free_rate: Option<f64>,
+  /// Trading costs paid from the portfolio at each rebalancing, none if missing
+  pub costs: Option<CostModel>,
+  /// Compared with the backtested portfolio over the same dates
+  pub benchmarks: Option<Vec<Benchmark>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceSchedule {
+  /// On the first trading day of each month
+  #[default]
+  Monthly,
+  /// On the first trading day of each quarter
+  Quarterly,
+  /// When a weight drifts from its target by more than the threshold, e.g. 0.05
+  Drift { threshold: f64 },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct BacktestResponse {
+  pub tickers: Vec<String>,
+  pub method: WeightsMethod,
+  pub dates: Vec<NaiveDate>,
+  /// Portfolio value at the close of each date, net of costs
+  pub values: Vec<f64>,
+  /// Rebalancings in date order, the first one is the initial investment
+  pub rebalances: Vec<Rebalancing>,
+  /// Average yearly turnover, traded value as a share of portfolio value
+  pub turnover: f64,
+  /// Sum of costs of all rebalancings
+  pub total_cost: f64,
+  /// Net of costs
+  pub stats: PerformanceStats,
+  /// In the order of requested benchmarks
+  pub benchmarks: Vec<BenchmarkComparison>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Rebalancing {
+  pub date: NaiveDate,
+  /// Target weights calculated from the lookback window before the date
+  pub weights: Vec<f64>,
+  /// Traded value as a share of portfolio value
+  pub turnover: f64,
+  pub cost: f64,
+  /// False when the risk parity solver didn't converge and the weights were traded anyway
+  pub converged: bool,
+}
+
+/// Performance of a daily value series, returns and volatility are annualized
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct PerformanceStats {
+  pub total_return: f64,
+  /// Compound annual growth rate
+  pub cagr: f64,
+  pub volatility: f64,
+  pub sharpe: f64,
+  /// Largest peak to trough loss as a positive share
+  pub max_drawdown: f64,
+}
+
+/// JSON body of `POST /service/v1/risk`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct RiskRequest {
+  /// Tickers and window, weights are calculated from it unless given
+  pub weights: WeightsRequest,
+  /// Weights to measure in the order of tickers instead of calculated ones
+  pub portfolio_weights: Option<Vec<f64>>,
+  /// Confidence level of VaR and CVaR, 0.95 if missing
+  pub confidence: Option<f64>,
+}
+
+/// Risk of the portfolio rebalanced to its weights daily over the window
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RiskResponse {
+  pub tickers: Vec<String>,
+  pub weights: Vec<f64>,
+  /// First day with prices in the window
+  pub start_date: NaiveDate,
+  /// Last day with prices in the window
+  pub end_date: NaiveDate,
+  pub confidence: f64,
+  pub stats: RiskStats,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RiskStats {
+  /// Annualized
+  pub volatility: f64,
+  /// Daily loss share exceeded with `1 - confidence` probability, from observed returns
+  pub historical_var: f64,
+  /// Average daily loss share beyond the historical VaR
+  pub historical_cvar: f64,
+  /// Daily VaR assuming normally distributed returns
+  pub parametric_var: f64,
+  /// Daily CVaR assuming normally distributed returns
+  pub parametric_cvar: f64,
+  /// Largest peak to trough loss as a positive share
+  pub max_drawdown: f64,
+  /// Skewness of daily returns
+  pub skew: f64,
+  /// Excess kurtosis of daily returns
+  pub kurtosis: f64,
+  /// Weighted average of ticker volatilities over portfolio volatility
+  pub diversification_ratio: f64,
+}
+
+/// Portfolio to compare with, a single ticker like `{"SPY": 1}` or a fixed mix like 60/40
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct Benchmark {
+  /// Tickers joined with `/` if missing
+  pub name: Option<String>,
+  /// Weight per ticker, rebalanced back to them daily
+  pub weights: BTreeMap<String, f64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct BenchmarkComparison {
+  pub name: String,
+  pub stats: PerformanceStats,
+  /// Portfolio CAGR minus benchmark CAGR
+  pub excess_return: f64,
+  /// Annualized volatility of daily return differences
+  pub tracking_error: f64,
+  /// Annualized mean return difference per unit of tracking error
+  pub information_ratio: f64,
+  /// Sensitivity of portfolio returns to benchmark returns
+  pub beta: f64,
+  /// Correlation of daily returns
+  pub correlation: f64,
+}
+
+/// JSON body of `POST /service/v1/simulation`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct SimulationRequest {
+  /// Tickers and window returns are drawn from, weights are calculated from it unless given
+  pub weights: WeightsRequest,
+  /// Weights to simulate in the order of tickers instead of calculated ones
+  pub portfolio_weights: Option<Vec<f64>>,
+  pub horizon_years: f64,
+  /// Number of simulated paths, 1000 if missing, at most 100 million simulated days over all paths
+  pub paths: Option<u32>,
+  /// How daily returns are drawn, multivariate normal if missing
+  pub model: Option<SimulationModel>,
+  /// Length of bootstrapped blocks in days, 20 if missing
+  pub block_days: Option<u32>,
+  /// 1 if missing
+  pub initial_value: Option<f64>,
+  /// Added at the end of every month, negative for withdrawals
+  pub monthly_cash_flow: Option<f64>,
+  /// Value the probability of ending the horizon at or above is calculated for
+  pub target_value: Option<f64>,
+  /// Percentiles of value paths, 5, 25, 50, 75 and 95 if missing
+  pub percentiles: Option<Vec<f64>>,
+  /// Random generator seed, the same seed gives the same paths, 0 if missing
+  pub seed: Option<u64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationModel {
+  /// Daily log returns with the historical mean and covariances
+  #[default]
+  MultivariateNormal,
+  /// Blocks of consecutive historical days, keeps fat tails and volatility clustering
+  BlockBootstrap,
+}
+
+impl SimulationModel {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SimulationModel::MultivariateNormal => "multivariate_normal",
+      SimulationModel::BlockBootstrap => "block_bootstrap",
+    }
+  }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct SimulationResponse {
+  pub tickers: Vec<String>,
+  pub weights: Vec<f64>,
+  /// First day with prices in the window
+  pub start_date: NaiveDate,
+  /// Last day with prices in the window
+  pub end_date: NaiveDate,
+  /// Years from now of each month end values are given for, starting at 0
+  pub times: Vec<f64>,
+  /// Portfolio value at each time per requested percentile
+  pub percentiles: Vec<PercentilePath>,
+  /// Share of paths ending at or above the target value
+  pub target_probability: Option<f64>,
+  /// Share of paths which ran out of money, withdrawals stop there
+  pub depletion_probability: f64,
+  pub seed: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct PercentilePath {
+  pub percentile: f64,
+  pub values: Vec<f64>,
+}
+
+/// JSON body of `POST /service/v1/stress`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct StressTestRequest {
+  /// Tickers and window, weights are calculated from it unless given
+  pub weights: WeightsRequest,
+  /// Weights to replay in the order of tickers instead of calculated ones
+  pub portfolio_weights: Option<Vec<f64>>,
+  pub scenarios: Vec<StressScenario>,
+  /// Ticker standing in for a ticker in periods it has no data for, e.g. `{"BND": "AGG"}`
+  pub proxies: Option<BTreeMap<String, String>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StressScenario {
+  /// From the S&P 500 peak on 2007-10-09 to its trough on 2009-03-09
+  GlobalFinancialCrisis,
+  /// From the S&P 500 peak on 2020-02-19 to its trough on 2020-03-23
+  CovidCrash,
+  /// Stocks and bonds falling together from 2022-01-03 to 2022-10-12
+  RatesShock,
+  Custom {
+    name: String,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+  },
+}
+
+impl StressScenario {
+  pub fn name(&self) -> &str {
+    match self {
+      StressScenario::GlobalFinancialCrisis => "global_financial_crisis",
+      StressScenario::CovidCrash => "covid_crash",
+      StressScenario::RatesShock => "rates_shock",
+      StressScenario::Custom { name, .. } => name,
+    }
+  }
+
+  /// First and last day of the period
+  pub fn dates(&self) -> (NaiveDate, NaiveDate) {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    match self {
+      StressScenario::GlobalFinancialCrisis => (date(2007, 10, 9), date(2009, 3, 9)),
+      StressScenario::CovidCrash => (date(2020, 2, 19), date(2020, 3, 23)),
+      StressScenario::RatesShock => (date(2022, 1, 3), date(2022, 10, 12)),
+      StressScenario::Custom {
+        start_date,
+        end_date,
+        ..
+      } => (*start_date, *end_date),
+    }
+  }
+}
+
+/// Allocation held through a stress period without rebalancing
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct StressResult {
+  pub name: String,
+  /// First day with prices in the period
+  pub start_date: NaiveDate,
+  /// Last day with prices in the period
+  pub end_date: NaiveDate,
+  pub total_return: f64,
+  /// Largest peak to trough loss in the period as a positive share
+  pub max_drawdown: f64,
+  pub trough_date: NaiveDate,
+  /// First day back at the peak before the trough, missing when it hasn't recovered yet
+  pub recovery_date: Option<NaiveDate>,
+  /// Calendar days from the trough to the recovery
+  pub recovery_days: Option<u32>,
+  /// Return of each ticker times its weight in the order of tickers, sums up to the total return
+  pub contributions: Vec<f64>,
+  /// Tickers replayed with their proxies for lack of data in the period
+  pub proxies: Vec<ProxyUse>,
+  /// Tickers with neither data nor a proxy in the period, their weight is held as cash
+  pub missing_tickers: Vec<String>,
+  /// False when proxies or cash stand in for some tickers, so the replay isn't the portfolio's
+  /// actual history
+  pub actual_history: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ProxyUse {
+  pub ticker: String,
+  pub proxy: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct StressTestResponse {
+  pub tickers: Vec<String>,
+  pub weights: Vec<f64>,
+  /// In the order of requested scenarios
+  pub scenarios: Vec<StressResult>,
+}
+
+/// Query of `GET /service/v1/covariance`, same window defaults as `WeightsRequest`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct CovarianceQuery {
+  pub tickers: Vec<String>,
+  pub start_date: Option<NaiveDate>,
+  pub end_date: Option<NaiveDate>,
+}
+
+/// Annualized estimates weights are calculated from, matrices are in the order of tickers
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct CovarianceResponse {
+  pub tickers: Vec<String>,
+  /// First day with prices in the window
+  pub start_date: NaiveDate,
+  /// Last day with prices in the window
+  pub end_date: NaiveDate,
+  pub volatilities: Vec<f64>,
+  pub covariances: Vec<Vec<f64>>,
+  pub correlations: Vec<Vec<f64>>,
+  /// Present when the estimated matrix wasn't positive definite and got repaired
+  pub covariance_repair: Option<CovarianceRepair>,
+}
+
+/// JSON body of `POST /service/v1/black-litterman`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct BlackLittermanRequest {
+  /// Weights equilibrium returns are implied from
+  pub weights: WeightsRequest,
+  pub views: Vec<View>,
+  /// Scales covariances into equilibrium returns, 2.5 if missing
+  pub risk_aversion: Option<f64>,
+  /// Uncertainty of equilibrium returns relative to covariances, 0.05 if missing
+  pub tau: Option<f64>,
+}
+
+/// Annualized return expected of a ticker, or of its outperformance of another one
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct View {
+  pub ticker: String,
+  /// Makes the view relative, the return is the outperformance of this ticker
+  pub relative_to: Option<String>,
+  pub expected_return: f64,
+  /// Between 0 and 1, a view held with full confidence is met exactly
+  pub confidence: f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct BlackLittermanResponse {
+  /// Weights without views, equilibrium returns are implied from them
+  pub weights: WeightsResponse,
+  /// Annualized returns for which the weights are mean-variance optimal
+  pub equilibrium_returns: Vec<f64>,
+  /// Annualized equilibrium returns blended with the views
+  pub posterior_returns: Vec<f64>,
+  /// Unconstrained mean-variance weights of posterior returns, the same as weights without views
+  pub mean_variance_weights: Vec<f64>,
+  pub risk_aversion: f64,
+  pub tau: f64,
+}
+
+/// JSON body of `POST /service/v1/frontier`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct FrontierRequest {
+  /// Tickers, window and the weights positioned relative to the frontier
+  pub weights: WeightsRequest,
+  /// Annualized expected return of each ticker, historical over the window if missing
+  pub expected_returns: Option<Vec<f64>>,
+  /// Number of frontier points, 20 if missing
+  pub points: Option<u32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct FrontierResponse {
+  pub weights: WeightsResponse,
+  /// Annualized, the ones the frontier is computed with
+  pub expected_returns: Vec<f64>,
+  /// Long-only portfolios from minimum variance up to the highest expected return
+  pub points: Vec<FrontierPoint>,
+  pub portfolio: FrontierPosition,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct FrontierPoint {
+  /// Annualized
+  pub expected_return: f64,
+  /// Annualized
+  pub volatility: f64,
+  pub weights: Vec<f64>,
+}
+
+/// Where the weights sit relative to the frontier
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct FrontierPosition {
+  pub expected_return: f64,
+  pub volatility: f64,
+  /// Highest expected return on the frontier at the same volatility, missing below the minimum
+  /// variance
+  pub frontier_return: Option<f64>,
+  /// Lowest volatility on the frontier with at least the same expected return, missing above the
+  /// highest expected return
+  pub frontier_volatility: Option<f64>,
+}
+
+/// Query of `GET /service/v1/prices`, same window defaults as `WeightsRequest`
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default)]
+pub struct PricesQuery {
+  pub tickers: Vec<String>,
+  pub start_date: Option<NaiveDate>,
+  pub end_date: Option<NaiveDate>,
+}
+
+/// Adjusted close prices aligned to business days, one row per date
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct PricesResponse {
+  pub tickers: Vec<String>,
+  pub dates: Vec<NaiveDate>,
+  pub prices: Vec<Vec<f64>>,
+}
+
+#[cfg_attr(feature = "client", derive(serde::Serialize))]
+#[cfg_attr(feature = "server", derive(serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct SearchQuery {
   pub term: String,
 }
+
+/// Search result item as returned by Yahoo! Finance
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct TickerInfo {
+  pub symbol: String,
+  pub name: String,
+  pub exch_disp: String,
+  pub type_disp: String,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_parses_weights_methods() {
+    for method in WeightsMethod::ALL.iter() {
+      assert_eq!(method.as_str().parse::<WeightsMethod>(), Ok(*method));
+    }
+    assert!("min_variance".parse::<WeightsMethod>().is_err());
+  }
+}
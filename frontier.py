@@ -0,0 +1,106 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+import numpy as np
+from scipy.optimize import minimize
+
+import rpar
+
+
+def _historical_returns(prices):
+
+    # Annualised mean of daily log returns, the series covariances are
+    # estimated from
+    log_changes = (np.log(prices) - np.log(prices.shift(1))).iloc[1:, :]
+    return rpar.BUSINESS_DAYS_PER_YEAR * log_changes.mean().values
+
+
+def _solve(objective, constraints, initial_weights):
+
+    # Long only and fully invested
+    constraints = [{'type': 'eq', 'fun': lambda x: np.sum(x) - 1.0},
+                   {'type': 'ineq', 'fun': lambda x: x}] + constraints
+    result = minimize(fun=objective,
+                      x0=initial_weights,
+                      method='SLSQP',
+                      constraints=constraints,
+                      tol=rpar.TOLERANCE,
+                      options={'disp': False, 'maxiter': 1000})
+    if not result.success:
+        raise ValueError('frontier optimisation failed: {}'.format(
+            result.message))
+    return np.clip(result.x, 0.0, None)
+
+
+def _minimum_variance(covariances, expected_returns, target_return,
+                      initial_weights):
+
+    constraints = []
+    if target_return is not None:
+        constraints.append({'type': 'eq',
+                            'fun': lambda x: expected_returns @ x -
+                            target_return})
+    return _solve(lambda x: x @ covariances @ x, constraints, initial_weights)
+
+
+def _maximum_return(covariances, expected_returns, volatility,
+                    initial_weights):
+
+    return _solve(lambda x: -(expected_returns @ x),
+                  [{'type': 'ineq',
+                    'fun': lambda x: volatility ** 2 - x @ covariances @ x}],
+                  initial_weights)
+
+
+def efficient_frontier(prices, weights, expected_returns=None, points=20):
+
+    # Long only mean-variance frontier from the minimum variance portfolio up
+    # to the ticker with the highest expected return
+    covariances = rpar.get_covariances(prices)
+    expected_returns = (_historical_returns(prices) if expected_returns is None
+                        else np.asarray(expected_returns, dtype=float))
+    size = len(expected_returns)
+    equal_weights = np.full(size, 1 / size)
+
+    def point(x):
+        return {'expected_return': float(expected_returns @ x),
+                'volatility': float(np.sqrt(x @ covariances @ x)),
+                'weights': x.tolist()}
+
+    minimum_variance = _minimum_variance(covariances, expected_returns, None,
+                                         equal_weights)
+    lowest_return = float(expected_returns @ minimum_variance)
+    highest_return = float(np.max(expected_returns))
+
+    frontier = [point(minimum_variance)]
+    previous_weights = minimum_variance
+    for target_return in np.linspace(lowest_return, highest_return,
+                                     points)[1:]:
+        previous_weights = _minimum_variance(covariances, expected_returns,
+                                             target_return, previous_weights)
+        frontier.append(point(previous_weights))
+
+    # Where the portfolio sits: the best return the frontier gets at its
+    # volatility and the least volatility it needs for its return, missing
+    # when the frontier doesn't reach them
+    weights = np.asarray(weights, dtype=float)
+    portfolio_return = float(expected_returns @ weights)
+    portfolio_volatility = float(np.sqrt(weights @ covariances @ weights))
+    frontier_return = None
+    if portfolio_volatility >= frontier[0]['volatility']:
+        frontier_return = float(expected_returns @ _maximum_return(
+            covariances, expected_returns, portfolio_volatility,
+            minimum_variance))
+    frontier_volatility = None
+    if portfolio_return <= highest_return:
+        target_return = max(portfolio_return, lowest_return)
+        frontier_volatility = point(_minimum_variance(
+            covariances, expected_returns, target_return,
+            minimum_variance))['volatility']
+
+    return {'expected_returns': expected_returns.tolist(),
+            'points': frontier,
+            'portfolio': {'expected_return': portfolio_return,
+                          'volatility': portfolio_volatility,
+                          'frontier_return': frontier_return,
+                          'frontier_volatility': frontier_volatility}}
@@ -0,0 +1,124 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+import numpy as np
+import pandas as pd
+from scipy.stats import norm
+
+import rpar
+
+
+def _years(values):
+
+    return (values.index[-1] - values.index[0]).days / 365.25
+
+
+def _periods_per_year(values):
+
+    # Values are daily on business days like the prices they come from
+    if _years(values) <= 0 or len(values) < 3:
+        raise ValueError('period is too short to measure performance')
+    return rpar.BUSINESS_DAYS_PER_YEAR
+
+
+def performance_stats(values, risk_free_rate=0.0):
+
+    periods_per_year = _periods_per_year(values)
+    returns = values.pct_change().iloc[1:]
+
+    volatility = returns.std() * np.sqrt(periods_per_year)
+    drawdowns = 1 - values / values.cummax()
+    growth = values.iloc[-1] / values.iloc[0]
+
+    return {'total_return': float(growth - 1),
+            'cagr': float(growth ** (1 / _years(values)) - 1),
+            'volatility': float(volatility),
+            'sharpe': float((returns.mean() * periods_per_year - risk_free_rate) /
+                            volatility) if volatility > 0 else 0.0,
+            'max_drawdown': float(drawdowns.max())}
+
+
+def fixed_mix_values(prices, weights):
+
+    # Value of a mix rebalanced back to its weights every day, starting at 1
+    returns = prices.pct_change().iloc[1:] @ np.asarray(weights, dtype=float)
+    return pd.concat([pd.Series([1.0], index=prices.index[:1]),
+                      (1 + returns).cumprod()])
+
+
+def compare_with_benchmarks(values, benchmarks, risk_free_rate=0.0):
+
+    # Benchmarks are (name, {ticker: weight}) pairs valued on the same dates
+    # as the portfolio
+    if not benchmarks:
+        return []
+
+    periods_per_year = _periods_per_year(values)
+    returns = values.pct_change().iloc[1:]
+    stats = performance_stats(values, risk_free_rate)
+
+    comparisons = []
+    for name, mix in benchmarks:
+        tickers = list(mix)
+        prices = (rpar.get_prices(tickers, values.index[0].date(),
+                                  values.index[-1].date())
+                  .reindex(values.index).ffill())
+        missing_tickers = rpar.find_tickers_with_missing_data(prices)
+        if missing_tickers:
+            raise ValueError('missing benchmark data for tickers {}'.format(
+                ', '.join(missing_tickers)))
+
+        benchmark_values = fixed_mix_values(
+            prices, [mix[ticker] for ticker in tickers])
+        benchmark_returns = benchmark_values.pct_change().iloc[1:]
+        benchmark_stats = performance_stats(benchmark_values, risk_free_rate)
+
+        active_returns = returns - benchmark_returns
+        tracking_error = active_returns.std() * np.sqrt(periods_per_year)
+        benchmark_variance = benchmark_returns.var()
+
+        comparisons.append({
+            'name': name,
+            'stats': benchmark_stats,
+            'excess_return': stats['cagr'] - benchmark_stats['cagr'],
+            'tracking_error': float(tracking_error),
+            'information_ratio': float(
+                active_returns.mean() * periods_per_year / tracking_error)
+            if tracking_error > 0 else 0.0,
+            'beta': float(returns.cov(benchmark_returns) / benchmark_variance)
+            if benchmark_variance > 0 else 0.0,
+            'correlation': float(returns.corr(benchmark_returns))
+            if benchmark_variance > 0 and returns.var() > 0 else 0.0})
+
+    return comparisons
+
+
+def risk_stats(prices, weights, confidence=0.95):
+
+    # Value at risk and expected shortfall are daily losses as positive shares
+    weights = np.asarray(weights, dtype=float)
+    values = fixed_mix_values(prices, weights)
+    periods_per_year = _periods_per_year(values)
+    returns = values.pct_change().iloc[1:]
+    losses = -returns
+
+    historical_var = float(np.quantile(losses, confidence))
+    mean, std = returns.mean(), returns.std()
+    z_score = norm.ppf(confidence)
+
+    volatility = std * np.sqrt(periods_per_year)
+    asset_volatilities = (prices.pct_change().iloc[1:].std() *
+                          np.sqrt(periods_per_year))
+
+    return {'volatility': float(volatility),
+            'historical_var': historical_var,
+            'historical_cvar': float(losses[losses >= historical_var].mean()),
+            'parametric_var': float(z_score * std - mean),
+            'parametric_cvar': float(
+                std * norm.pdf(z_score) / (1 - confidence) - mean),
+            'max_drawdown': float((1 - values / values.cummax()).max()),
+            'skew': float(returns.skew()),
+            'kurtosis': float(returns.kurt()),
+            'diversification_ratio': float(
+                np.abs(weights) @ asset_volatilities.values / volatility)
+            if volatility > 0 else 1.0}
@@ -1,14 +1,31 @@
 #!/usr/bin/env python
 # coding: utf-8
 
+import datetime
+import io
+import json
+
 import numpy as np
 import pandas as pd
 import pandas_datareader.data as web
 import yfinance
-from scipy.optimize import minimize
+from scipy.optimize import OptimizeResult, minimize
+from scipy.stats import kurtosis, norm, skew
 
 yfinance.pdr_override()
 TOLERANCE = 1e-10
+# Prices are aligned to business days, daily statistics are annualised with
+# their number in a year
+BUSINESS_DAYS_PER_YEAR = 261
+# Largest deviation of risk contribution shares from the risk budget for
+# solved weights to count as converged
+DEVIATION_TOLERANCE = 1e-4
+# Smallest eigenvalue a correlation matrix may have before it is repaired
+EIGENVALUE_FLOOR = 1e-8
+# Confidence of expected shortfall unless given, and the number of tail
+# quantiles the Cornish-Fisher estimate averages
+SHORTFALL_CONFIDENCE = 0.95
+SHORTFALL_QUANTILES = 100
 
 
 def _allocation_risk(weights, covariances):
@@ -69,7 +86,12 @@ def _risk_budget_objective_error(weights, args):
     return error
 
 
-def _get_risk_parity_weights(covariances, assets_risk_budget, initial_weights):
+def _get_risk_parity_weights(covariances, assets_risk_budget,
+                             initial_weights=None):
+
+    # Initial weights: equally weighted unless given
+    if initial_weights is None:
+        initial_weights = [1 / len(assets_risk_budget)] * len(assets_risk_budget)
 
     # Restrictions to consider in the optimisation: only long positions whose
     # sum equals 100%
@@ -85,36 +107,479 @@ def _get_risk_parity_weights(covariances, assets_risk_budget, initial_weights):
                                tol=TOLERANCE,
                                options={'disp': False})
 
-    # Recover the weights from the optimised object
-    weights = optimize_result.x
+    # It returns the optimised object, weights are its x
+    return optimize_result
+
+
+def _spinu_start(covariances, assets_risk_budget, initial_weights=None):
+
+    # Initial weights (inverse volatility where missing or not positive)
+    # scaled onto the solution's y'Σy = 1 surface
+    budget = np.asarray(assets_risk_budget, dtype=float)
+    y = budget / np.sqrt(np.diag(covariances))
+    if initial_weights is not None:
+        initial_weights = np.asarray(initial_weights, dtype=float)
+        y = np.where(initial_weights > 0, initial_weights, y)
+    return y / np.sqrt(y @ covariances @ y)
+
+
+def _get_risk_parity_weights_newton(covariances, assets_risk_budget,
+                                    initial_weights=None, max_iterations=100):
+
+    # Spinu's convex formulation: y minimising y'Σy / 2 - b'log(y) has risk
+    # contributions proportional to the budget b, weights are y normalised.
+    # The objective is self-concordant, so damped Newton steps keep y positive
+    budget = np.asarray(assets_risk_budget, dtype=float)
+    y = _spinu_start(covariances, budget, initial_weights)
+    success = False
+    for iteration in range(1, max_iterations + 1):
+        gradient = covariances @ y - budget / y
+        hessian = covariances + np.diag(budget / y ** 2)
+        step = np.linalg.solve(hessian, gradient)
+        decrement = np.sqrt(max(gradient @ step, 0.0))
+        if decrement < 1e-10:
+            success = True
+            break
+        y = y - (step if decrement < 0.25 else step / (1 + decrement))
+
+    return OptimizeResult(
+        x=y / np.sum(y), success=success, nit=iteration,
+        message=('Newton decrement below 1e-10' if success
+                 else 'Iteration limit reached'))
+
+
+def _get_risk_parity_weights_ccd(covariances, assets_risk_budget,
+                                 initial_weights=None, max_iterations=10000):
+
+    # Cyclical coordinate descent on Spinu's formulation, each coordinate has
+    # a closed form minimum given the others: σ_ii y_i² + c_i y_i - b_i = 0
+    budget = np.asarray(assets_risk_budget, dtype=float)
+    variances = np.diag(covariances)
+    y = _spinu_start(covariances, budget, initial_weights)
+    covariance_products = covariances @ y
+    success = False
+    for iteration in range(1, max_iterations + 1):
+        largest_change = 0.0
+        for i in range(len(y)):
+            others = covariance_products[i] - variances[i] * y[i]
+            y_i = (-others + np.sqrt(others ** 2 + 4 * variances[i] * budget[i])
+                   ) / (2 * variances[i])
+            covariance_products += covariances[:, i] * (y_i - y[i])
+            largest_change = max(largest_change, abs(y_i - y[i]) / y_i)
+            y[i] = y_i
+        if largest_change < 1e-12:
+            success = True
+            break
+
+    return OptimizeResult(
+        x=y / np.sum(y), success=success, nit=iteration,
+        message=('Relative change below 1e-12' if success
+                 else 'Iteration limit reached'))
+
+
+def _risk_contribution_shares(weights, covariances):
 
-    # It returns the optimised weights
-    return weights
+    weights = np.matrix(weights)
+    return np.asarray(_assets_risk_contribution_to_allocation_risk(
+        weights, covariances) / _allocation_risk(weights, covariances)).flatten()
 
 
-def get_weights(prices):
+def _get_turnover_aware_weights(covariances, assets_risk_budget,
+                                previous_weights, turnover_penalty=0.0,
+                                max_turnover=None):
 
-    # We calculate the covariance matrix
+    # Squared deviation of risk contribution shares from the budget traded
+    # off against turnover from the previous weights, absolute values are
+    # smoothed for SLSQP
+    budget = np.asarray(assets_risk_budget, dtype=float)
+
+    def turnover(x):
+        return np.sum(np.sqrt((x - previous_weights) ** 2 + 1e-12))
+
+    def objective(x):
+        return (np.sum((_risk_contribution_shares(x, covariances) - budget) ** 2)
+                + turnover_penalty * turnover(x))
+
+    constraints = [{'type': 'eq', 'fun': lambda x: np.sum(x) - 1.0},
+                   {'type': 'ineq', 'fun': lambda x: x}]
+    if max_turnover is not None:
+        constraints.append({'type': 'ineq',
+                            'fun': lambda x: max_turnover - turnover(x)})
+
+    return minimize(fun=objective,
+                    x0=previous_weights,
+                    method='SLSQP',
+                    constraints=constraints,
+                    tol=TOLERANCE,
+                    options={'disp': False, 'maxiter': 1000})
+
+
+def _get_net_limited_weights(covariances, assets_risk_budget, signs,
+                             max_net_share, initial_weights):
+
+    # Absolute weights with risk contribution shares closest to the budget
+    # whose signed sum stays within the net exposure limit, on covariances of
+    # sign flipped assets
+    budget = np.asarray(assets_risk_budget, dtype=float)
+
+    def objective(x):
+        return np.sum((_risk_contribution_shares(x, covariances) - budget) ** 2)
+
+    constraints = ({'type': 'eq', 'fun': lambda x: np.sum(x) - 1.0},
+                   {'type': 'ineq', 'fun': lambda x: x},
+                   {'type': 'ineq', 'fun': lambda x: max_net_share - signs @ x},
+                   {'type': 'ineq', 'fun': lambda x: max_net_share + signs @ x})
+
+    return minimize(fun=objective,
+                    x0=initial_weights,
+                    method='SLSQP',
+                    constraints=constraints,
+                    tol=TOLERANCE,
+                    options={'disp': False, 'maxiter': 1000})
+
+
+def _cornish_fisher_shortfall(weights, returns, confidence):
+
+    # Normal tail quantiles adjusted for the skew and excess kurtosis of
+    # portfolio returns, averaged over the tail
+    portfolio_returns = returns @ weights
+    z = norm.ppf((np.arange(SHORTFALL_QUANTILES) + 0.5) /
+                 SHORTFALL_QUANTILES * (1 - confidence))
+    s = skew(portfolio_returns)
+    k = kurtosis(portfolio_returns)
+    adjusted_z = (z + (z ** 2 - 1) * s / 6 + (z ** 3 - 3 * z) * k / 24 -
+                  (2 * z ** 3 - 5 * z) * s ** 2 / 36)
+    return -(np.mean(portfolio_returns) +
+             np.std(portfolio_returns) * np.mean(adjusted_z))
+
+
+def _shortfall_contributions(weights, returns, confidence, estimator):
+
+    # Euler contributions of each asset to the expected shortfall of daily
+    # returns, they sum up to it
+    weights = np.asarray(weights, dtype=float)
+    if estimator == 'historical':
+        tail_size = max(int(len(returns) * (1 - confidence)), 1)
+        tail = np.argsort(returns @ weights)[:tail_size]
+        return -weights * np.mean(returns[tail], axis=0)
+    elif estimator == 'cornish_fisher':
+        # The estimate is homogeneous in weights, so weights times its
+        # gradient add up to it
+        step = 1e-6
+        gradient = np.array([
+            (_cornish_fisher_shortfall(weights + step * unit, returns,
+                                       confidence) -
+             _cornish_fisher_shortfall(weights - step * unit, returns,
+                                       confidence)) / (2 * step)
+            for unit in np.eye(len(weights))])
+        return weights * gradient
+    else:
+        raise ValueError('unknown expected shortfall estimator {}'.format(
+            estimator))
+
+
+def _shortfall_contribution_shares(weights, returns, confidence, estimator):
+
+    contributions = _shortfall_contributions(weights, returns, confidence,
+                                             estimator)
+    return contributions / np.sum(contributions)
+
+
+def _get_shortfall_risk_parity_weights(returns, assets_risk_budget,
+                                       confidence, estimator, initial_weights):
+
+    # Squared deviation of expected shortfall contribution shares from the
+    # budget, the historical estimate is piecewise smooth so SLSQP is started
+    # from volatility risk parity which is usually close
+    budget = np.asarray(assets_risk_budget, dtype=float)
+    if np.sum(_shortfall_contributions(initial_weights, returns, confidence,
+                                       estimator)) <= 0:
+        raise ValueError('expected shortfall is not positive, there are no '
+                         'losses to balance')
+
+    def objective(x):
+        return np.sum((_shortfall_contribution_shares(
+            x, returns, confidence, estimator) - budget) ** 2)
+
+    constraints = ({'type': 'eq', 'fun': lambda x: np.sum(x) - 1.0},
+                   {'type': 'ineq', 'fun': lambda x: x})
+
+    return minimize(fun=objective,
+                    x0=initial_weights,
+                    method='SLSQP',
+                    constraints=constraints,
+                    tol=TOLERANCE,
+                    options={'disp': False, 'maxiter': 1000})
+
+
+def _repair_covariances(covariances):
+
+    # Eigenvalue clipping of the correlation matrix, rescaled back to unit
+    # diagonal so variances stay as estimated
+    volatilities = np.sqrt(np.diag(covariances))
+    correlations = covariances / np.outer(volatilities, volatilities)
+    eigenvalues, eigenvectors = np.linalg.eigh(correlations)
+    if eigenvalues[0] >= EIGENVALUE_FLOOR:
+        return covariances, None
+
+    correlations = (eigenvectors @
+                    np.diag(np.maximum(eigenvalues, EIGENVALUE_FLOOR)) @
+                    eigenvectors.T)
+    scale = 1 / np.sqrt(np.diag(correlations))
+    correlations = correlations * np.outer(scale, scale)
+
+    return (correlations * np.outer(volatilities, volatilities),
+            {'min_eigenvalue': float(eigenvalues[0]),
+             'clipped_eigenvalues': int(np.sum(eigenvalues < EIGENVALUE_FLOOR))})
+
+
+def get_covariances(prices):
+
+    return get_covariances_with_repair(prices)[0]
+
+
+def get_covariances_with_repair(prices):
+
+    # Annualised covariance matrix of daily log returns
     log_changes = (np.log(prices) -
                    np.log(prices.shift(1))).iloc[1:, :]
-    covariances = 365.0 * log_changes.cov().values
+    covariances = BUSINESS_DAYS_PER_YEAR * log_changes.cov().values
+
+    flat_tickers = prices.columns[~(np.diag(covariances) > 0)].tolist()
+    if flat_tickers:
+        raise ValueError('no price changes for tickers {}'.format(
+            ', '.join(flat_tickers)))
+
+    # Short or gappy histories may give a matrix which isn't positive
+    # definite, it is repaired to the nearest one with clipped eigenvalues
+    return _repair_covariances(covariances)
+
+
+def get_covariance_matrices(prices):
+
+    # The covariance matrix weights are calculated from, with the correlations
+    # and volatilities it implies
+    covariances, repair = get_covariances_with_repair(prices)
+    volatilities = np.sqrt(np.diag(covariances))
+    correlations = covariances / np.outer(volatilities, volatilities)
+
+    return {'volatilities': volatilities.tolist(),
+            'covariances': covariances.tolist(),
+            'correlations': correlations.tolist(),
+            'covariance_repair': repair}
+
+
+def get_volatility(prices, weights):
+
+    # Annualised volatility of the portfolio
+    weights = np.asarray(weights, dtype=float)
+    return float(np.sqrt(weights @ get_covariances(prices) @ weights))
+
+
+RISK_PARITY_SOLVERS = {'newton': _get_risk_parity_weights_newton,
+                       'coordinate_descent': _get_risk_parity_weights_ccd,
+                       'slsqp': _get_risk_parity_weights}
+
 
-    # The desired contribution of each asset to the portfolio risk: we want all
-    # asset to contribute equally
-    assets_risk_budget = [1 / prices.shape[1]] * prices.shape[1]
+def get_weights(prices, *args, **kwargs):
 
-    # Initial weights: equally weighted
-    init_weights = [1 / prices.shape[1]] * prices.shape[1]
+    return get_weights_with_diagnostics(prices, *args, **kwargs)[0]
 
-    # Optimisation process of weights
-    weights = _get_risk_parity_weights(
-        covariances, assets_risk_budget, init_weights)
+
+def get_weights_with_diagnostics(prices, assets_risk_budget=None,
+                                 method='risk_parity', target_volatility=None,
+                                 max_leverage=None,
+                                 tolerance=DEVIATION_TOLERANCE,
+                                 fail_unconverged=False, solver='newton',
+                                 previous_weights=None, turnover_penalty=0.0,
+                                 max_turnover=None, signs=None,
+                                 gross_exposure=1.0, max_net_exposure=None,
+                                 shortfall_estimator='historical',
+                                 shortfall_confidence=SHORTFALL_CONFIDENCE):
+
+    # Diagnostics are only there for the risk parity solver, other methods
+    # are closed form
+    diagnostics = None
+
+    # We calculate the covariance matrix
+    covariances, covariance_repair = get_covariances_with_repair(prices)
+
+    # Long short weights are solved as absolute weights: their risk
+    # contributions are those of long only weights on the covariances of
+    # assets with flipped signs, so absolute risk contributions are balanced
+    long_short = signs is not None
+    signs = (np.ones(prices.shape[1]) if signs is None
+             else np.asarray(signs, dtype=float))
+    if np.any(np.abs(signs) != 1):
+        raise ValueError('long short signs must be 1 or -1')
+    solved_covariances = covariances * np.outer(signs, signs)
+    if max_net_exposure is not None and not long_short:
+        raise ValueError('net exposure limit applies to long short weights '
+                         'only')
+
+    # Leverage is capped at the gross exposure asked for unless given
+    if max_leverage is None:
+        max_leverage = gross_exposure if long_short else 1.0
+    if long_short and max_leverage < gross_exposure:
+        raise ValueError('max leverage {} is below the gross exposure '
+                         '{}'.format(max_leverage, gross_exposure))
+
+    # Previous weights are a warm start, with turnover limits they also
+    # anchor the solution
+    if previous_weights is not None:
+        previous_weights = np.abs(np.asarray(previous_weights, dtype=float))
+        previous_weights = previous_weights / np.sum(previous_weights)
+    turnover_limited = bool(turnover_penalty) or max_turnover is not None
+    if turnover_limited and previous_weights is None:
+        raise ValueError('turnover limits need previous weights')
+    if turnover_limited and method != 'risk_parity':
+        raise ValueError('turnover limits apply to risk parity weights only')
+    if turnover_limited and long_short:
+        raise ValueError('turnover limits apply to long only weights')
+
+    if method == 'equal_weight':
+        weights = np.full(prices.shape[1], 1 / prices.shape[1])
+    elif method == 'inverse_volatility':
+        # Naive risk parity: ignores correlations between assets
+        inverse_volatilities = 1 / np.sqrt(np.diag(covariances))
+        weights = inverse_volatilities / np.sum(inverse_volatilities)
+    elif method in ('risk_parity', 'expected_shortfall_risk_parity'):
+        # The desired contribution of each asset to the portfolio risk: unless
+        # given we want all asset to contribute equally
+        if assets_risk_budget is None:
+            assets_risk_budget = [1 / prices.shape[1]] * prices.shape[1]
+        else:
+            assets_risk_budget = list(
+                np.array(assets_risk_budget) / np.sum(assets_risk_budget))
+
+        # Optimisation process of weights
+        if solver not in RISK_PARITY_SOLVERS:
+            raise ValueError('unknown risk parity solver {}'.format(solver))
+        # Spinu's formulation takes the log of every asset's weight, so
+        # budgets with zero shares are left to SLSQP
+        positive_budget = np.min(assets_risk_budget) > 0
+        if not positive_budget:
+            solver = 'slsqp'
+        if method == 'expected_shortfall_risk_parity':
+            # Daily log returns, the series covariances are estimated from,
+            # with flipped signs of short assets
+            returns = (np.log(prices) - np.log(prices.shift(1))).iloc[1:, :]
+            solved_returns = returns.values * signs
+            solver = 'slsqp'
+            optimize_result = _get_shortfall_risk_parity_weights(
+                solved_returns, assets_risk_budget, shortfall_confidence,
+                shortfall_estimator,
+                RISK_PARITY_SOLVERS['newton' if positive_budget else 'slsqp'](
+                    solved_covariances, assets_risk_budget,
+                    previous_weights).x)
+        elif turnover_limited:
+            solver = 'slsqp'
+            optimize_result = _get_turnover_aware_weights(
+                solved_covariances, assets_risk_budget, previous_weights,
+                turnover_penalty, max_turnover)
+        else:
+            optimize_result = RISK_PARITY_SOLVERS[solver](
+                solved_covariances, assets_risk_budget, previous_weights)
+
+        # Exact risk parity fixes the net exposure of the signs, a tighter
+        # limit is traded off against the budget
+        net_limited = (method == 'risk_parity' and
+                       max_net_exposure is not None and
+                       abs(signs @ optimize_result.x) * gross_exposure >
+                       max_net_exposure)
+        if net_limited:
+            solver = 'slsqp'
+            optimize_result = _get_net_limited_weights(
+                solved_covariances, assets_risk_budget, signs,
+                max_net_exposure / gross_exposure, optimize_result.x)
+        weights = optimize_result.x
+
+        # The solver may stop without reaching the budget, so the result is
+        # checked against it unless turnover or net exposure limits keep it
+        # away on purpose
+        if method == 'expected_shortfall_risk_parity':
+            objective = float(optimize_result.fun)
+            shares = _shortfall_contribution_shares(
+                weights, solved_returns, shortfall_confidence,
+                shortfall_estimator)
+        else:
+            objective = float(_risk_budget_objective_error(
+                weights, [solved_covariances, assets_risk_budget]))
+            shares = _risk_contribution_shares(weights, solved_covariances)
+        max_deviation = float(np.max(np.abs(
+            shares - np.asarray(assets_risk_budget))))
+        diagnostics = {
+            'solver': solver,
+            'converged': bool(optimize_result.success and
+                              (turnover_limited or net_limited or
+                               max_deviation <= tolerance)),
+            'status': str(optimize_result.message),
+            'iterations': int(optimize_result.nit),
+            'objective': objective,
+            'max_deviation': max_deviation,
+            'tolerance': tolerance}
+        if fail_unconverged and not diagnostics['converged']:
+            raise ValueError(
+                'risk parity solver did not converge: {}, risk contributions '
+                'deviate from the budget by up to {:.2e}'.format(
+                    diagnostics['status'], max_deviation))
+    else:
+        raise ValueError('unknown weights method {}'.format(method))
+
+    if long_short:
+        weights = signs * weights * gross_exposure
+        if (max_net_exposure is not None and method != 'risk_parity' and
+                abs(np.sum(weights)) > max_net_exposure + 1e-9):
+            raise ValueError('net exposure {:.4f} exceeds the limit'.format(
+                abs(np.sum(weights))))
+
+    # Scaled up to the target volatility as far as the leverage cap allows or
+    # down leaving the rest in cash
+    if target_volatility is not None:
+        volatility = np.sqrt(weights @ covariances @ weights)
+        if volatility > 0:
+            weights = weights * min(target_volatility / volatility,
+                                    max_leverage / np.sum(np.abs(weights)))
 
     # Convert the weights to a pandas Series
     weights = pd.Series(weights, index=prices.columns, name='weight')
 
     # It returns the optimised weights
-    return weights
+    return weights, diagnostics, covariance_repair
+
+
+def get_risk_contributions(prices, weights):
+
+    # Contributions of each asset to the portfolio risk as shares of it
+    return _risk_contribution_shares(weights,
+                                     get_covariances(prices)).tolist()
+
+
+def export_weights(tickers, names, weights, risk_contributions, metadata,
+                   file_format):
+    table = pd.DataFrame({'ticker': tickers,
+                          'name': names,
+                          'weight': weights,
+                          'risk_contribution': risk_contributions})
+    metadata = pd.DataFrame(metadata, columns=['key', 'value'])
+
+    if file_format == 'csv':
+        # metadata goes first as comment lines, e.g. `# method: risk_parity`
+        header = ''.join('# {}: {}\n'.format(key, value)
+                         for key, value in metadata.itertuples(index=False))
+        return (header + table.to_csv(index=False)).encode('utf-8')
+    elif file_format == 'json':
+        return json.dumps({'weights': table.to_dict(orient='records'),
+                           'metadata': dict(metadata.values.tolist())},
+                          indent=2).encode('utf-8')
+    elif file_format == 'xlsx':
+        output = io.BytesIO()
+        with pd.ExcelWriter(output, engine='openpyxl') as writer:
+            table.to_excel(writer, sheet_name='weights', index=False)
+            metadata.to_excel(writer, sheet_name='metadata', index=False)
+        return output.getvalue()
+    else:
+        raise ValueError('unknown export format {}'.format(file_format))
 
 
 def get_prices(yahoo_tickers, start_date, end_date):
@@ -137,6 +602,175 @@ def get_prices(yahoo_tickers, start_date, end_date):
   else:
     return pd.DataFrame({yahoo_tickers[0]: prices.values}, index=prices.index)
 
+def get_latest_prices(yahoo_tickers, end_date):
+
+    # A couple of weeks back is enough to get over weekends and holidays
+    prices = get_prices(yahoo_tickers,
+                        end_date - datetime.timedelta(days=14),
+                        end_date)
+    latest_prices = prices.ffill().iloc[-1]
+
+    missing_tickers = latest_prices[latest_prices.isnull()].index.tolist()
+    if missing_tickers:
+        raise ValueError('missing latest prices for tickers {}'.format(
+            ', '.join(missing_tickers)))
+
+    return latest_prices
+
+
+def _allocate(prices, weights, amount, lot_sizes=None, fractional=False):
+
+    latest_prices = prices.iloc[-1]
+    target_weights = pd.Series(weights, index=prices.columns)
+
+    if fractional:
+        return amount * target_weights / latest_prices
+
+    lots = (pd.Series(lot_sizes or {}, dtype=float)
+            .reindex(prices.columns, fill_value=1.0))
+    lot_values = lots * latest_prices
+    covariances = get_covariances(prices)
+
+    def tracking_error(counts):
+        return _tracking_error(counts * lot_values / amount - target_weights,
+                               covariances)
+
+    # Whole lots below the target first, then the leftover cash buys lots one
+    # by one, each time the one reducing tracking error the most
+    lot_counts = np.floor(amount * target_weights / lot_values)
+    leftover = amount - (lot_counts * lot_values).sum()
+    error = tracking_error(lot_counts)
+    while True:
+        best_ticker = None
+        for ticker in prices.columns:
+            if lot_values[ticker] > leftover:
+                continue
+            counts = lot_counts.copy()
+            counts[ticker] += 1
+            candidate_error = tracking_error(counts)
+            if candidate_error < error:
+                best_ticker, error = ticker, candidate_error
+        if best_ticker is None:
+            break
+        lot_counts[best_ticker] += 1
+        leftover -= lot_values[best_ticker]
+
+    return lot_counts * lots
+
+
+def _tracking_error(weights_difference, covariances):
+
+    # Annualised volatility of the difference between two portfolios
+    weights_difference = np.asarray(weights_difference, dtype=float)
+    return float(np.sqrt(weights_difference @ covariances @ weights_difference))
+
+
+def allocate(prices, weights, amount, lot_sizes=None, fractional=False):
+
+    latest_prices = prices.iloc[-1]
+    target_weights = pd.Series(weights, index=prices.columns)
+    lots = (pd.Series(lot_sizes or {}, dtype=float)
+            .reindex(prices.columns, fill_value=1.0))
+
+    quantities = _allocate(prices, weights, amount, lot_sizes, fractional)
+    values = quantities * latest_prices
+    realized_weights = values / amount
+
+    positions = [{'ticker': ticker,
+                  'price': float(latest_prices[ticker]),
+                  'lot_size': float(lots[ticker]),
+                  'quantity': float(quantities[ticker]),
+                  'value': float(values[ticker]),
+                  'target_weight': float(target_weights[ticker]),
+                  'realized_weight': float(realized_weights[ticker])}
+                 for ticker in prices.columns]
+
+    return {'positions': positions,
+            'invested': float(values.sum()),
+            'leftover_cash': float(amount - values.sum()),
+            'tracking_error': _tracking_error(
+                realized_weights - target_weights, get_covariances(prices))}
+
+
+def trade_costs(trade_values, cost_model=None):
+
+    # Fixed fee per trade plus commission and asset class slippage in basis
+    # points of the traded value
+    traded_values = trade_values.abs()
+    if not cost_model:
+        return traded_values * 0.0
+
+    asset_classes = (pd.Series(cost_model['asset_classes'], dtype=object)
+                     .reindex(traded_values.index))
+    slippage_bps = (asset_classes.map(cost_model['slippage_bps'])
+                    .fillna(0.0).astype(float))
+    rates = (cost_model['commission_bps'] + slippage_bps) / 10000.0
+    fees = (traded_values > 0) * cost_model['fixed_fee']
+
+    return traded_values * rates + fees
+
+
+def rebalance(prices, weights, holdings, cash, lot_sizes=None,
+              fractional=False, cost_model=None):
+
+    # Tickers held but missing in the target portfolio are sold out at their
+    # latest prices
+    latest_prices = prices.iloc[-1]
+    sold_out_tickers = [ticker for ticker in holdings
+                        if ticker not in latest_prices.index]
+    if sold_out_tickers:
+        latest_prices = pd.concat([
+            latest_prices,
+            get_latest_prices(sold_out_tickers, prices.index[-1].date())])
+
+    current_quantities = (pd.Series(holdings, dtype=float)
+                          .reindex(latest_prices.index, fill_value=0.0))
+    portfolio_value = (current_quantities * latest_prices).sum() + cash
+    if portfolio_value <= 0:
+        raise ValueError('nothing to rebalance, portfolio value is zero')
+
+    # Costs are paid from the portfolio, so the allocated amount shrinks
+    # until the remaining cash covers them
+    amount = portfolio_value
+    for _ in range(10):
+        allocated_quantities = _allocate(prices, weights, amount,
+                                         lot_sizes, fractional)
+        target_quantities = allocated_quantities.reindex(latest_prices.index,
+                                                         fill_value=0.0)
+        trade_quantities = target_quantities - current_quantities
+        trade_values = trade_quantities * latest_prices
+        costs = trade_costs(trade_values, cost_model)
+        remaining_cash = (portfolio_value -
+                          (target_quantities * latest_prices).sum() -
+                          costs.sum())
+        if remaining_cash >= -1e-9 * portfolio_value:
+            break
+        amount += remaining_cash
+    else:
+        raise ValueError('trading costs exceed the portfolio value')
+
+    trades = [{'ticker': ticker,
+               'price': float(latest_prices[ticker]),
+               'current_quantity': float(current_quantities[ticker]),
+               'target_quantity': float(target_quantities[ticker]),
+               'quantity': float(trade_quantities[ticker]),
+               'value': float(trade_values[ticker]),
+               'cost': float(costs[ticker])}
+              for ticker in latest_prices.index]
+
+    target_weights = pd.Series(weights, index=prices.columns)
+    realized_weights = (allocated_quantities * prices.iloc[-1] /
+                        portfolio_value)
+
+    return {'trades': trades,
+            'portfolio_value': float(portfolio_value),
+            'turnover': float(trade_values.abs().sum() / portfolio_value),
+            'total_cost': float(costs.sum()),
+            'remaining_cash': float(max(remaining_cash, 0.0)),
+            'tracking_error': _tracking_error(
+                realized_weights - target_weights, get_covariances(prices))}
+
+
 def find_tickers_with_missing_data(prices):
     result = []
     if prices.isnull().values.any():
@@ -0,0 +1,2 @@
+pub mod py_bridge;
+pub mod search;
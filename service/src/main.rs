@@ -1,15 +1,15 @@
 mod actix_anyhow;
+mod openapi;
 
 use crate::actix_anyhow::AnyhowErrorWrapper;
 use actix_cors::Cors;
 use actix_web::error;
-use actix_web::{get, http, web::Json, App, HttpServer, Responder};
-use anyhow::{anyhow, Context};
+use actix_web::{get, http, post, web::Json, App, HttpResponse, HttpServer, Responder};
+use anyhow::anyhow;
 use listenfd::ListenFd;
 use pyo3::prelude::*;
-use pyo3::types::IntoPyDict;
-use pyo3::types::PyList;
-use serde_qs::actix::QsQuery;
+use serde_qs::actix::{QsQuery, QsQueryConfig};
+use service::{py_bridge, search::find_tickers};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -21,6 +21,7 @@ async fn main() -> std::io::Result<()> {
 
   let server = HttpServer::new(|| {
     App::new()
+      .app_data(QsQueryConfig::default().qs_config(openapi::query_config()))
       .wrap(
         Cors::default()
           .allowed_origin(
@@ -42,7 +43,21 @@ async fn main() -> std::io::Result<()> {
           .max_age(3600),
       )
       .service(get_weights)
+      .service(post_weights)
+      .service(get_weights_export)
+      .service(post_weights_history)
+      .service(post_allocation)
+      .service(post_rebalance)
+      .service(post_backtest)
+      .service(post_risk)
+      .service(get_covariance)
+      .service(post_black_litterman)
+      .service(post_frontier)
+      .service(post_simulation)
+      .service(post_stress)
+      .service(get_prices)
       .service(get_search)
+      .service(get_openapi)
   });
 
   // if we are given a tcp listener on listen fd 0, we use that one
@@ -57,95 +72,168 @@ async fn main() -> std::io::Result<()> {
 
 #[get("/service/v1/weights")]
 async fn get_weights(query: QsQuery<core::GetWeightsQuery>) -> actix_web::Result<impl Responder> {
-  let weights_result = Python::with_gil(|py| {
-    calc_weights(py, query.tickers.iter().map(String::as_str).collect())
-      .map_err(|e| AnyhowErrorWrapper::from(anyhow!("error calculating weigths: {}", e)))
+  let request = core::WeightsRequest::from(query.into_inner());
+  with_python("error calculating weigths", |py| {
+    py_bridge::calc_weights(py, &request)
+      .map(|weights_result| weights_result.map(|core::WeightsResponse { weights, .. }| weights))
+  })
+}
+
+#[post("/service/v1/weights")]
+async fn post_weights(request: Json<core::WeightsRequest>) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error calculating weights", |py| {
+    py_bridge::calc_weights(py, &request)
+  })
+}
+
+#[get("/service/v1/weights/export")]
+async fn get_weights_export(
+  query: QsQuery<core::ExportWeightsQuery>,
+) -> actix_web::Result<HttpResponse> {
+  let query = query.into_inner();
+  let content = call_python("error exporting weights", |py| {
+    py_bridge::export_weights(py, &query)
   })?;
 
-  match weights_result {
-    Ok(weights) => Ok(Json(weights)),
-    Err(anyhow_error) => Err(error::ErrorBadRequest(anyhow_error)),
-  }
+  let content_type = match query.format {
+    core::ExportFormat::Csv => "text/csv; charset=utf-8",
+    core::ExportFormat::Json => "application/json",
+    core::ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+  };
+  Ok(
+    HttpResponse::Ok()
+      .content_type(content_type)
+      .header(
+        http::header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"weights.{}\"", query.format.as_str()),
+      )
+      .body(content),
+  )
 }
 
-#[get("/service/v1/search")]
-async fn get_search(query: QsQuery<core::SearchQuery>) -> actix_web::Result<impl Responder> {
-  match find_tickers(&query).await {
-    Ok(result) => Ok(Json(result)),
-    Err(anyhow_error) => Err(error::ErrorBadRequest(anyhow_error)),
-  }
+#[post("/service/v1/weights/history")]
+async fn post_weights_history(
+  request: Json<core::WeightsHistoryRequest>,
+) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error calculating weights history", |py| {
+    py_bridge::weights_history(py, &request)
+  })
 }
 
-async fn find_tickers(query: &core::SearchQuery) -> anyhow::Result<serde_json::Value> {
-  let url = format!("https://finance.yahoo.com/_finance_doubledown/api/resource/searchassist;searchTerm={}?device=console&returnMeta=true", &query.term);
-  let mut json: serde_json::Value = reqwest::get(&url)
-    .await
-    .with_context(|| format!("Can't fetch URL {}", &url))?
-    .json()
-    .await
-    .with_context(|| "Can't parse json")?;
-  // will return Value::Null null if not found in json
-  let items = json["data"]["items"].take();
-  Ok(items)
-}
-
-// TODO move to py_bridge
-fn calc_weights(py: Python, tickers: Vec<&str>) -> PyResult<anyhow::Result<Vec<f64>>> {
-  let sys = py.import("sys")?;
-  sys.get("path")?.call_method(
-    "extend",
-    (vec![
-      // TODO use env var for this
-      "",
-      "/Users/alunacharskii/.local/lib/python3.7/site-packages",
-      "/opt/anaconda3/lib/python3.7/site-packages",
-    ],),
-    None,
-  )?;
-
-  // Default calculating of YTD date interval
-  let datetime = py.import("datetime")?;
-  let today = datetime.getattr("date")?.call_method("today", (), None)?;
-  let current_year: u32 = today.getattr("year")?.extract()?;
-  let year_ago = today.call_method(
-    "replace",
-    (),
-    Some([("year", current_year - 1)].into_py_dict(py)),
-  )?;
-
-  let rpar = py.import("rpar")?;
-  let prices = rpar.call_method("get_prices", (tickers, year_ago, today), None)?;
-
-  let missing_data = rpar
-    .call_method("find_tickers_with_missing_data", (prices,), None)?
-    .downcast::<PyList>()?;
-
-  Ok(if missing_data.is_empty() {
-    let weights: Vec<f64> = rpar
-      .call_method("get_weights", (prices,), None)?
-      .extract()?;
-
-    Ok(weights)
-  } else {
-    let tickers: Vec<&str> = missing_data.extract()?;
-    Err(anyhow!("missing data for tickers {}", tickers.join(", ")))
+#[post("/service/v1/allocation")]
+async fn post_allocation(
+  request: Json<core::AllocationRequest>,
+) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error allocating", |py| py_bridge::allocate(py, &request))
+}
+
+#[post("/service/v1/rebalance")]
+async fn post_rebalance(
+  request: Json<core::RebalanceRequest>,
+) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error rebalancing", |py| py_bridge::rebalance(py, &request))
+}
+
+#[post("/service/v1/backtest")]
+async fn post_backtest(request: Json<core::BacktestRequest>) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error backtesting", |py| py_bridge::backtest(py, &request))
+}
+
+#[post("/service/v1/risk")]
+async fn post_risk(request: Json<core::RiskRequest>) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error calculating risk", |py| {
+    py_bridge::calc_risk(py, &request)
   })
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use anyhow::Result;
+#[get("/service/v1/covariance")]
+async fn get_covariance(
+  query: QsQuery<core::CovarianceQuery>,
+) -> actix_web::Result<impl Responder> {
+  let query = query.into_inner();
+  with_python("error calculating covariance", |py| {
+    py_bridge::calc_covariance(py, &query)
+  })
+}
 
-  #[ignore]
-  #[actix_rt::test]
-  async fn it_can_find_tickers() -> Result<()> {
-    let tickers = find_tickers(&core::SearchQuery {
-      term: "spy".to_string(),
-    })
-    .await?;
-    println!("Response: {}", serde_json::to_string(&tickers)?);
+#[post("/service/v1/black-litterman")]
+async fn post_black_litterman(
+  request: Json<core::BlackLittermanRequest>,
+) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error blending views", |py| {
+    py_bridge::black_litterman(py, &request)
+  })
+}
+
+#[post("/service/v1/frontier")]
+async fn post_frontier(request: Json<core::FrontierRequest>) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error calculating efficient frontier", |py| {
+    py_bridge::frontier(py, &request)
+  })
+}
+
+#[post("/service/v1/simulation")]
+async fn post_simulation(
+  request: Json<core::SimulationRequest>,
+) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error simulating portfolio", |py| {
+    py_bridge::simulate(py, &request)
+  })
+}
+
+#[post("/service/v1/stress")]
+async fn post_stress(request: Json<core::StressTestRequest>) -> actix_web::Result<impl Responder> {
+  let request = request.into_inner();
+  with_python("error replaying stress scenarios", |py| {
+    py_bridge::stress_test(py, &request)
+  })
+}
+
+#[get("/service/v1/prices")]
+async fn get_prices(query: QsQuery<core::PricesQuery>) -> actix_web::Result<impl Responder> {
+  let query = query.into_inner();
+  with_python("error loading prices", |py| {
+    py_bridge::load_prices_table(py, &query)
+  })
+}
 
-    Ok(())
+/// Runs a python bridge call, python failures are internal errors and calculation ones are bad requests
+fn call_python<T, F>(context: &str, call: F) -> actix_web::Result<T>
+where
+  F: FnOnce(Python) -> PyResult<anyhow::Result<T>>,
+{
+  let result = Python::with_gil(|py| {
+    call(py).map_err(|e| AnyhowErrorWrapper::from(anyhow!("{}: {}", context, e)))
+  })?;
+
+  result.map_err(error::ErrorBadRequest)
+}
+
+fn with_python<T, F>(context: &str, call: F) -> actix_web::Result<Json<T>>
+where
+  F: FnOnce(Python) -> PyResult<anyhow::Result<T>>,
+{
+  call_python(context, call).map(Json)
+}
+
+#[get("/service/v1/search")]
+async fn get_search(query: QsQuery<core::SearchQuery>) -> actix_web::Result<impl Responder> {
+  match find_tickers(&query).await {
+    Ok(result) => Ok(Json(result)),
+    Err(anyhow_error) => Err(error::ErrorBadRequest(anyhow_error)),
   }
 }
+
+#[get("/service/v1/openapi.json")]
+async fn get_openapi() -> impl Responder {
+  Json(openapi::spec())
+}
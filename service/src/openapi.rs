@@ -0,0 +1,302 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+/// Query string parsing the parameters of the spec need: arrays are repeated `tickers[]` keys,
+/// which generated clients percent encode and only non-strict mode decodes
+pub fn query_config() -> serde_qs::Config {
+  serde_qs::Config::new(5, false)
+}
+
+/// OpenAPI 3 document of the service API, served at `/service/v1/openapi.json`
+pub fn spec() -> Value {
+  let mut gen = SchemaSettings::openapi3().into_generator();
+
+  let paths = json!({
+    "/service/v1/weights": {
+      "get": get_operation::<core::GetWeightsQuery, Vec<f64>>(
+        &mut gen,
+        "Risk parity weights of tickers over the last year",
+      ),
+      "post": post_operation::<core::WeightsRequest, core::WeightsResponse>(
+        &mut gen,
+        "Risk parity weights of tickers over a custom window and risk budget",
+      ),
+    },
+    "/service/v1/weights/export": {
+      "get": {
+        "summary": "Risk parity weights with risk contributions as a csv, json or xlsx file",
+        "parameters": query_parameters::<core::ExportWeightsQuery>(&mut gen),
+        "responses": {
+          "200": {
+            "description": "Weights file attachment",
+            "content": {
+              "text/csv": { "schema": { "type": "string" } },
+              "application/json": { "schema": { "type": "object" } },
+              "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet": {
+                "schema": { "type": "string", "format": "binary" },
+              },
+            },
+          },
+          "400": { "description": "Invalid request or failed calculation" },
+        },
+      },
+    },
+    "/service/v1/weights/history": {
+      "post": post_operation::<core::WeightsHistoryRequest, core::WeightsHistoryResponse>(
+        &mut gen,
+        "Weights and risk contributions calculated on a rolling window at regular steps",
+      ),
+    },
+    "/service/v1/allocation": {
+      "post": post_operation::<core::AllocationRequest, core::AllocationResponse>(
+        &mut gen,
+        "Whole lot quantities closest to risk parity weights for an amount to invest",
+      ),
+    },
+    "/service/v1/rebalance": {
+      "post": post_operation::<core::RebalanceRequest, core::RebalanceResponse>(
+        &mut gen,
+        "Trades from current holdings to risk parity weights at the latest prices",
+      ),
+    },
+    "/service/v1/backtest": {
+      "post": post_operation::<core::BacktestRequest, core::BacktestResponse>(
+        &mut gen,
+        "Historical performance of the portfolio rebalanced on a schedule",
+      ),
+    },
+    "/service/v1/risk": {
+      "post": post_operation::<core::RiskRequest, core::RiskResponse>(
+        &mut gen,
+        "Volatility, VaR, CVaR, drawdown and diversification of given or calculated weights",
+      ),
+    },
+    "/service/v1/covariance": {
+      "get": get_operation::<core::CovarianceQuery, core::CovarianceResponse>(
+        &mut gen,
+        "Covariance and correlation matrices with volatilities weights are calculated from",
+      ),
+    },
+    "/service/v1/black-litterman": {
+      "post": post_operation::<core::BlackLittermanRequest, core::BlackLittermanResponse>(
+        &mut gen,
+        "Equilibrium returns implied by the weights blended with views, and their mean-variance weights",
+      ),
+    },
+    "/service/v1/frontier": {
+      "post": post_operation::<core::FrontierRequest, core::FrontierResponse>(
+        &mut gen,
+        "Long-only mean-variance efficient frontier and where the weights sit relative to it",
+      ),
+    },
+    "/service/v1/simulation": {
+      "post": post_operation::<core::SimulationRequest, core::SimulationResponse>(
+        &mut gen,
+        "Monte Carlo percentiles of portfolio value over a horizon with monthly cash flows",
+      ),
+    },
+    "/service/v1/stress": {
+      "post": post_operation::<core::StressTestRequest, core::StressTestResponse>(
+        &mut gen,
+        "Drawdown, recovery and loss contributions of the weights in historical stress periods",
+      ),
+    },
+    "/service/v1/prices": {
+      "get": get_operation::<core::PricesQuery, core::PricesResponse>(
+        &mut gen,
+        "Adjusted close prices of tickers, the same ones weights are calculated from",
+      ),
+    },
+    "/service/v1/search": {
+      // Yahoo's items are passed through as they are, null when nothing matches
+      "get": get_operation::<core::SearchQuery, Option<Vec<core::TickerInfo>>>(
+        &mut gen,
+        "Tickers matching a search term, null when there are none",
+      ),
+    },
+    "/service/v1/openapi.json": {
+      "get": {
+        "summary": "This document",
+        "responses": {
+          "200": json_response(json!({ "type": "object" })),
+        },
+      },
+    },
+  });
+
+  json!({
+    "openapi": "3.0.3",
+    "info": {
+      "title": "Risk Balanced Portfolio service",
+      "version": env!("CARGO_PKG_VERSION"),
+    },
+    "paths": paths,
+    "components": {
+      "schemas": gen.definitions(),
+    },
+  })
+}
+
+fn get_operation<Query: JsonSchema, Out: JsonSchema>(
+  gen: &mut SchemaGenerator,
+  summary: &str,
+) -> Value {
+  json!({
+    "summary": summary,
+    "parameters": query_parameters::<Query>(gen),
+    "responses": responses::<Out>(gen),
+  })
+}
+
+fn post_operation<In: JsonSchema, Out: JsonSchema>(
+  gen: &mut SchemaGenerator,
+  summary: &str,
+) -> Value {
+  json!({
+    "summary": summary,
+    "requestBody": {
+      "required": true,
+      "content": {
+        "application/json": { "schema": gen.subschema_for::<In>() },
+      },
+    },
+    "responses": responses::<Out>(gen),
+  })
+}
+
+/// Query structs are flattened into parameters, arrays are repeated keys with brackets
+/// `tickers[]=SPY&tickers[]=TLT` as `serde_qs` reads them
+fn query_parameters<Query: JsonSchema>(gen: &mut SchemaGenerator) -> Vec<Value> {
+  let object = Query::json_schema(gen)
+    .into_object()
+    .object
+    .unwrap_or_default();
+  let required = object.required;
+  object
+    .properties
+    .into_iter()
+    .map(|(name, schema)| {
+      let schema = json!(schema);
+      let is_array = schema["type"] == "array";
+      json!({
+        "required": required.contains(&name),
+        "name": if is_array { format!("{}[]", name) } else { name },
+        "in": "query",
+        "style": "form",
+        "explode": true,
+        "schema": schema,
+      })
+    })
+    .collect()
+}
+
+fn responses<Out: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+  json!({
+    "200": json_response(json!(gen.subschema_for::<Out>())),
+    "400": { "description": "Invalid request or failed calculation" },
+  })
+}
+
+fn json_response(schema: Value) -> Value {
+  json!({
+    "description": "Success",
+    "content": {
+      "application/json": { "schema": schema },
+    },
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+  #[test]
+  fn it_matches_committed_spec() {
+    let generated = spec();
+    if std::env::var("UPDATE_OPENAPI").is_ok() {
+      let json = serde_json::to_string_pretty(&generated).unwrap();
+      std::fs::write(SPEC_PATH, json + "\n").unwrap();
+    }
+    let committed: Value =
+      serde_json::from_str(&std::fs::read_to_string(SPEC_PATH).unwrap()).unwrap();
+    assert!(
+      generated == committed,
+      "{} is out of date, regenerate it with `UPDATE_OPENAPI=1 cargo test -p service openapi`",
+      SPEC_PATH
+    );
+  }
+
+  /// Query string of a GET operation as generated clients send it, names percent encoded and
+  /// arrays exploded into repeated keys
+  fn spec_query(path: &str, values: &[(&str, &[&str])]) -> String {
+    let spec = spec();
+    let parameters = spec["paths"][path]["get"]["parameters"].as_array().unwrap();
+    let mut pairs = Vec::new();
+    for (name, values) in values {
+      assert!(
+        parameters
+          .iter()
+          .any(|parameter| parameter["name"] == *name),
+        "{} isn't a parameter of {}",
+        name,
+        path
+      );
+      let name = name.replace('[', "%5B").replace(']', "%5D");
+      for value in values.iter() {
+        pairs.push(format!("{}={}", name, value));
+      }
+    }
+    pairs.join("&")
+  }
+
+  #[test]
+  fn it_parses_spec_shaped_queries() {
+    let query = spec_query(
+      "/service/v1/weights/export",
+      &[
+        ("tickers[]", &["SPY", "TLT"]),
+        ("risk_budget[]", &["0.6", "0.4"]),
+        ("names[]", &["Stocks", "Bonds"]),
+        ("format", &["csv"]),
+      ],
+    );
+    let parsed: core::ExportWeightsQuery = query_config().deserialize_str(&query).unwrap();
+    assert_eq!(parsed.tickers, vec!["SPY", "TLT"]);
+    assert_eq!(parsed.risk_budget, Some(vec![0.6, 0.4]));
+    assert_eq!(
+      parsed.names,
+      Some(vec!["Stocks".to_string(), "Bonds".to_string()])
+    );
+
+    let query = spec_query(
+      "/service/v1/prices",
+      &[("tickers[]", &["SPY"]), ("start_date", &["2020-01-02"])],
+    );
+    let parsed: core::PricesQuery = query_config().deserialize_str(&query).unwrap();
+    assert_eq!(parsed.tickers, vec!["SPY"]);
+  }
+
+  #[test]
+  fn it_documents_every_route() {
+    let spec = spec();
+    for line in include_str!("main.rs").lines().map(str::trim) {
+      for method in &["get", "post"] {
+        let prefix = format!("#[{}(\"", method);
+        if let Some(path) = line
+          .strip_prefix(&prefix)
+          .and_then(|rest| rest.strip_suffix("\")]"))
+        {
+          assert!(
+            spec["paths"][path][method].is_object(),
+            "{} {} is missing in the OpenAPI spec",
+            method,
+            path
+          );
+        }
+      }
+    }
+  }
+}
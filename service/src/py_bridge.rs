@@ -0,0 +1,1354 @@
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::IntoPyDict;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Like `?` for calculation errors wrapped into `PyResult`, returns them as `Ok(Err(..))`
+macro_rules! try_calc {
+  ($result:expr) => {
+    match $result {
+      Ok(value) => value,
+      Err(err) => return Ok(Err(err.into())),
+    }
+  };
+}
+
+fn import_rpar<'p>(py: Python<'p>) -> PyResult<&'p PyModule> {
+  import_module(py, "rpar")
+}
+
+fn import_module<'p>(py: Python<'p>, name: &str) -> PyResult<&'p PyModule> {
+  let sys = py.import("sys")?;
+  sys.get("path")?.call_method(
+    "extend",
+    (vec![
+      // TODO use env var for this
+      "",
+      "/Users/alunacharskii/.local/lib/python3.7/site-packages",
+      "/opt/anaconda3/lib/python3.7/site-packages",
+    ],),
+    None,
+  )?;
+  py.import(name)
+}
+
+/// Python `ValueError`s stand for invalid input, so they are calculation errors
+fn value_error_as_calc<T>(py: Python, result: PyResult<T>) -> PyResult<anyhow::Result<T>> {
+  match result {
+    Ok(value) => Ok(Ok(value)),
+    Err(err) if err.is_instance::<PyValueError>(py) => Ok(Err(anyhow!("{}", err))),
+    Err(err) => Err(err),
+  }
+}
+
+/// Reads a JSON serializable python result into a core type
+fn from_python<T: DeserializeOwned>(py: Python, value: &PyAny) -> PyResult<anyhow::Result<T>> {
+  let json: String = py
+    .import("json")?
+    .call_method1("dumps", (value,))?
+    .extract()?;
+  Ok(serde_json::from_str(&json).map_err(|err| anyhow!("can't read python result: {}", err)))
+}
+
+fn py_date<'p>(py: Python<'p>, date: NaiveDate) -> PyResult<&'p PyAny> {
+  py.import("datetime")?
+    .getattr("date")?
+    .call_method1("fromisoformat", (date.to_string(),))
+}
+
+/// Converts optional window bounds to python dates, defaulting to the last year
+fn date_range<'p>(
+  py: Python<'p>,
+  start_date: Option<NaiveDate>,
+  end_date: Option<NaiveDate>,
+) -> PyResult<(&'p PyAny, &'p PyAny)> {
+  let end_date = match end_date {
+    Some(end_date) => py_date(py, end_date)?,
+    None => py
+      .import("datetime")?
+      .getattr("date")?
+      .call_method0("today")?,
+  };
+  let start_date = match start_date {
+    Some(start_date) => py_date(py, start_date)?,
+    None => {
+      let end_year: u32 = end_date.getattr("year")?.extract()?;
+      end_date.call_method(
+        "replace",
+        (),
+        Some([("year", end_year - 1)].into_py_dict(py)),
+      )?
+    }
+  };
+  Ok((start_date, end_date))
+}
+
+/// Loads a price frame for tickers failing if any of them has gaps in the window
+fn load_prices<'p>(
+  py: Python<'p>,
+  rpar: &'p PyModule,
+  tickers: &[String],
+  start_date: Option<NaiveDate>,
+  end_date: Option<NaiveDate>,
+) -> PyResult<anyhow::Result<&'p PyAny>> {
+  let (start_date, end_date) = date_range(py, start_date, end_date)?;
+  let prices = rpar.call_method("get_prices", (tickers.to_vec(), start_date, end_date), None)?;
+
+  let missing_data = rpar
+    .call_method("find_tickers_with_missing_data", (prices,), None)?
+    .downcast::<PyList>()?;
+
+  Ok(if missing_data.is_empty() {
+    Ok(prices)
+  } else {
+    let tickers: Vec<&str> = missing_data.extract()?;
+    Err(anyhow!("missing data for tickers {}", tickers.join(", ")))
+  })
+}
+
+pub fn calc_weights(
+  py: Python,
+  request: &core::WeightsRequest,
+) -> PyResult<anyhow::Result<core::WeightsResponse>> {
+  let rpar = import_rpar(py)?;
+  let (_, weights) = try_calc!(weights_with_prices(py, rpar, request)?);
+  Ok(Ok(weights))
+}
+
+/// Per ticker risk budget, group budgets are subdivided among their tickers
+fn asset_risk_budget(request: &core::WeightsRequest) -> anyhow::Result<Option<Vec<f64>>> {
+  let groups = match &request.risk_budget_groups {
+    Some(groups) => groups,
+    None => return Ok(request.risk_budget.clone()),
+  };
+  if request.risk_budget.is_some() {
+    return Err(anyhow!(
+      "risk budget and risk budget groups can't be combined"
+    ));
+  }
+
+  let mut budget: Vec<Option<f64>> = vec![None; request.tickers.len()];
+  for group in groups {
+    if group.tickers.is_empty() {
+      return Err(anyhow!("risk budget group {} has no tickers", group.name));
+    }
+    // Zero budgets would leave assets the solver can't weigh
+    if !(group.budget > 0.0 && group.budget.is_finite()) {
+      return Err(anyhow!(
+        "risk budget of group {} must be positive",
+        group.name
+      ));
+    }
+    let sub_budget = match &group.sub_budget {
+      Some(sub_budget) => {
+        if sub_budget.len() != group.tickers.len() {
+          return Err(anyhow!(
+            "sub budget of group {} has {} values for {} tickers",
+            group.name,
+            sub_budget.len(),
+            group.tickers.len()
+          ));
+        }
+        if !sub_budget
+          .iter()
+          .all(|share| *share > 0.0 && share.is_finite())
+        {
+          return Err(anyhow!(
+            "sub budget shares of group {} must be positive",
+            group.name
+          ));
+        }
+        let total: f64 = sub_budget.iter().sum();
+        sub_budget.iter().map(|share| share / total).collect()
+      }
+      None => vec![1.0 / group.tickers.len() as f64; group.tickers.len()],
+    };
+
+    for (ticker, share) in group.tickers.iter().zip(sub_budget) {
+      let i = request
+        .tickers
+        .iter()
+        .position(|requested| requested == ticker)
+        .ok_or_else(|| anyhow!("ticker {} of group {} isn't requested", ticker, group.name))?;
+      if budget[i].is_some() {
+        return Err(anyhow!("ticker {} is in more than one group", ticker));
+      }
+      budget[i] = Some(group.budget * share);
+    }
+  }
+
+  let budget = request
+    .tickers
+    .iter()
+    .zip(budget)
+    .map(|(ticker, budget)| budget.ok_or_else(|| anyhow!("ticker {} isn't in any group", ticker)))
+    .collect::<anyhow::Result<Vec<f64>>>()?;
+  Ok(Some(budget))
+}
+
+/// Signs of long-short weights from sides or else views
+fn long_short_signs(request: &core::WeightsRequest) -> anyhow::Result<Option<Vec<f64>>> {
+  let long_short = match &request.long_short {
+    Some(long_short) => long_short,
+    None => return Ok(None),
+  };
+  let signs: Vec<f64> = match (&long_short.sides, &long_short.views) {
+    (Some(sides), _) => sides.iter().map(core::PositionSide::sign).collect(),
+    (None, Some(views)) => {
+      if views.contains(&0.0) {
+        return Err(anyhow!("views can't be zero, they decide long or short"));
+      }
+      views.iter().map(|view| view.signum()).collect()
+    }
+    (None, None) => return Err(anyhow!("long short needs sides or views")),
+  };
+  if signs.len() != request.tickers.len() {
+    return Err(anyhow!(
+      "long short has {} sides or views for {} tickers",
+      signs.len(),
+      request.tickers.len()
+    ));
+  }
+  Ok(Some(signs))
+}
+
+fn check_weights_request(request: &core::WeightsRequest) -> anyhow::Result<()> {
+  asset_risk_budget(request)?;
+  long_short_signs(request)?;
+  if let Some(risk_budget) = &request.risk_budget {
+    if risk_budget.len() != request.tickers.len() {
+      return Err(anyhow!(
+        "risk budget has {} values for {} tickers",
+        risk_budget.len(),
+        request.tickers.len()
+      ));
+    }
+    if risk_budget
+      .iter()
+      .any(|share| !share.is_finite() || *share < 0.0)
+      || risk_budget.iter().sum::<f64>() <= 0.0
+    {
+      return Err(anyhow!(
+        "risk budget must be finite, can't be negative and must sum up above zero"
+      ));
+    }
+  }
+  if let Some(previous_weights) = &request.previous_weights {
+    if previous_weights.len() != request.tickers.len() {
+      return Err(anyhow!(
+        "{} previous weights for {} tickers",
+        previous_weights.len(),
+        request.tickers.len()
+      ));
+    }
+    if request.long_short.is_none() && previous_weights.iter().any(|weight| *weight < 0.0) {
+      return Err(anyhow!("previous weights can't be negative when long only"));
+    }
+    if previous_weights
+      .iter()
+      .map(|weight| weight.abs())
+      .sum::<f64>()
+      <= 0.0
+    {
+      return Err(anyhow!("previous weights can't be all zero"));
+    }
+  }
+  let negative = |value: Option<f64>| value.is_some_and(|value| value < 0.0);
+  if negative(request.turnover_penalty) || negative(request.max_turnover) {
+    return Err(anyhow!(
+      "turnover penalty and max turnover can't be negative"
+    ));
+  }
+  let non_positive = |value: Option<f64>| value.is_some_and(|value| value <= 0.0);
+  if non_positive(request.target_volatility)
+    || non_positive(request.max_leverage)
+    || non_positive(request.tolerance)
+  {
+    return Err(anyhow!(
+      "target volatility, max leverage and tolerance must be positive"
+    ));
+  }
+  if let Some(confidence) = request.shortfall_confidence {
+    if confidence <= 0.0 || confidence >= 1.0 {
+      return Err(anyhow!("shortfall confidence must be between 0 and 1"));
+    }
+  }
+  if let Some(long_short) = &request.long_short {
+    if non_positive(long_short.gross_exposure) || negative(long_short.max_net_exposure) {
+      return Err(anyhow!(
+        "gross exposure must be positive and max net exposure can't be negative"
+      ));
+    }
+    // Scaling to a target volatility would otherwise shrink the book below its gross exposure
+    let gross_exposure = long_short.gross_exposure.unwrap_or(1.0);
+    if request
+      .max_leverage
+      .is_some_and(|max_leverage| max_leverage < gross_exposure)
+    {
+      return Err(anyhow!(
+        "max leverage is below the gross exposure {}",
+        gross_exposure
+      ));
+    }
+  }
+  Ok(())
+}
+
+/// Keyword arguments of `rpar.get_weights` besides prices, for requests which passed
+/// `check_weights_request`
+fn weights_options<'p>(py: Python<'p>, request: &core::WeightsRequest) -> PyResult<&'p PyDict> {
+  let options = PyDict::new(py);
+  options.set_item(
+    "assets_risk_budget",
+    asset_risk_budget(request).unwrap_or_default(),
+  )?;
+  options.set_item("method", request.method.unwrap_or_default().as_str())?;
+  options.set_item("target_volatility", request.target_volatility)?;
+  if let Some(max_leverage) = request.max_leverage {
+    options.set_item("max_leverage", max_leverage)?;
+  }
+  if let Some(tolerance) = request.tolerance {
+    options.set_item("tolerance", tolerance)?;
+  }
+  options.set_item(
+    "fail_unconverged",
+    request.fail_unconverged.unwrap_or(false),
+  )?;
+  options.set_item("solver", request.solver.unwrap_or_default().as_str())?;
+  options.set_item("previous_weights", request.previous_weights.clone())?;
+  options.set_item("turnover_penalty", request.turnover_penalty.unwrap_or(0.0))?;
+  options.set_item("max_turnover", request.max_turnover)?;
+  options.set_item(
+    "shortfall_estimator",
+    request.shortfall_estimator.unwrap_or_default().as_str(),
+  )?;
+  if let Some(confidence) = request.shortfall_confidence {
+    options.set_item("shortfall_confidence", confidence)?;
+  }
+  if let Some(long_short) = &request.long_short {
+    options.set_item("signs", long_short_signs(request).unwrap_or_default())?;
+    options.set_item("gross_exposure", long_short.gross_exposure.unwrap_or(1.0))?;
+    options.set_item("max_net_exposure", long_short.max_net_exposure)?;
+  }
+  Ok(options)
+}
+
+/// Whole weights can only be bought with the cash at hand
+fn check_unleveraged(weights: &core::WeightsResponse) -> anyhow::Result<()> {
+  if weights.weights.iter().any(|weight| *weight < 0.0) {
+    Err(anyhow!("short weights can't be bought"))
+  } else if weights.gross_exposure > 1.0 + 1e-9 {
+    Err(anyhow!(
+      "weights with gross exposure {:.4} need leverage",
+      weights.gross_exposure
+    ))
+  } else {
+    Ok(())
+  }
+}
+
+/// Calculates weights keeping the price frame they are based on for further calculations
+fn weights_with_prices<'p>(
+  py: Python<'p>,
+  rpar: &'p PyModule,
+  request: &core::WeightsRequest,
+) -> PyResult<anyhow::Result<(&'p PyAny, core::WeightsResponse)>> {
+  try_calc!(check_weights_request(request));
+
+  let prices = try_calc!(load_prices(
+    py,
+    rpar,
+    &request.tickers,
+    request.start_date,
+    request.end_date,
+  )?);
+  let dates = try_calc!(price_dates(prices)?);
+  if dates.is_empty() {
+    return Ok(Err(anyhow!("no prices in the window")));
+  }
+
+  let result = try_calc!(value_error_as_calc(
+    py,
+    rpar.call_method(
+      "get_weights_with_diagnostics",
+      (prices,),
+      Some(weights_options(py, request)?),
+    )
+  )?);
+  let weights: Vec<f64> = result.get_item(0)?.extract()?;
+  let solver = try_calc!(from_python(py, result.get_item(1)?)?);
+  let covariance_repair = try_calc!(from_python(py, result.get_item(2)?)?);
+  let risk_contributions: Vec<f64> = rpar
+    .call_method1("get_risk_contributions", (prices, weights.clone()))?
+    .extract()?;
+  let volatility: f64 = rpar
+    .call_method1("get_volatility", (prices, weights.clone()))?
+    .extract()?;
+
+  let turnover = request
+    .previous_weights
+    .as_ref()
+    .map(|previous_weights| turnover(&weights, previous_weights));
+
+  Ok(Ok((
+    prices,
+    core::WeightsResponse {
+      tickers: request.tickers.clone(),
+      gross_exposure: weights.iter().map(|weight| weight.abs()).sum(),
+      weights,
+      risk_contributions,
+      method: request.method.unwrap_or_default(),
+      start_date: dates[0],
+      end_date: dates[dates.len() - 1],
+      volatility,
+      solver,
+      covariance_repair,
+      turnover,
+    },
+  )))
+}
+
+/// Sum of absolute weight changes with both weights scaled to a unit gross exposure, the scale
+/// rpar limits turnover on before leverage, cash or gross exposure are applied
+fn turnover(weights: &[f64], previous_weights: &[f64]) -> f64 {
+  let gross_exposure = |weights: &[f64]| -> f64 { weights.iter().map(|weight| weight.abs()).sum() };
+  let (scale, previous_scale) = (gross_exposure(weights), gross_exposure(previous_weights));
+  weights
+    .iter()
+    .zip(previous_weights.iter())
+    .map(|(weight, previous_weight)| (weight / scale - previous_weight / previous_scale).abs())
+    .sum()
+}
+
+/// Calculates weights and renders them as a downloadable file
+pub fn export_weights(
+  py: Python,
+  query: &core::ExportWeightsQuery,
+) -> PyResult<anyhow::Result<Vec<u8>>> {
+  let weights = try_calc!(calc_weights(py, &query.weights_request())?);
+  let names = query.names.clone().unwrap_or_default();
+  let names: Vec<&str> = (0..weights.tickers.len())
+    .map(|i| names.get(i).map(String::as_str).unwrap_or(""))
+    .collect();
+  let metadata = vec![
+    ("method", weights.method.to_string()),
+    ("start_date", weights.start_date.to_string()),
+    ("end_date", weights.end_date.to_string()),
+    ("calculated_at", chrono::Utc::now().to_rfc3339()),
+  ];
+
+  let rpar = import_rpar(py)?;
+  let content: &PyBytes = rpar
+    .call_method1(
+      "export_weights",
+      (
+        weights.tickers.clone(),
+        names,
+        weights.weights.clone(),
+        weights.risk_contributions.clone(),
+        metadata,
+        query.format.as_str(),
+      ),
+    )?
+    .downcast()?;
+
+  Ok(Ok(content.as_bytes().to_vec()))
+}
+
+fn check_lot_sizes(lot_sizes: &Option<BTreeMap<String, f64>>) -> anyhow::Result<()> {
+  match lot_sizes {
+    Some(lot_sizes) if lot_sizes.values().any(|lot_size| *lot_size <= 0.0) => {
+      Err(anyhow!("lot sizes must be positive"))
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Benchmarks as (name, mix) pairs `performance.compare_with_benchmarks` expects
+fn benchmark_mixes(
+  benchmarks: &Option<Vec<core::Benchmark>>,
+) -> anyhow::Result<Vec<(String, BTreeMap<String, f64>)>> {
+  benchmarks
+    .iter()
+    .flatten()
+    .map(|benchmark| {
+      if benchmark.weights.is_empty() {
+        return Err(anyhow!("benchmark has no tickers"));
+      }
+      let name = benchmark.name.clone().unwrap_or_else(|| {
+        let tickers: Vec<&str> = benchmark.weights.keys().map(String::as_str).collect();
+        tickers.join("/")
+      });
+      Ok((name, benchmark.weights.clone()))
+    })
+    .collect()
+}
+
+#[derive(Deserialize)]
+struct Allocation {
+  positions: Vec<core::Position>,
+  invested: f64,
+  leftover_cash: f64,
+  tracking_error: f64,
+}
+
+/// Quantities approximating the weights for an amount at the last prices of the weights window
+pub fn allocate(
+  py: Python,
+  request: &core::AllocationRequest,
+) -> PyResult<anyhow::Result<core::AllocationResponse>> {
+  if request.amount <= 0.0 {
+    return Ok(Err(anyhow!("amount to invest must be positive")));
+  }
+  try_calc!(check_lot_sizes(&request.lot_sizes));
+  let benchmarks = try_calc!(benchmark_mixes(&request.benchmarks));
+
+  let rpar = import_rpar(py)?;
+  let (prices, weights) = try_calc!(weights_with_prices(py, rpar, &request.weights)?);
+  try_calc!(check_unleveraged(&weights));
+  let allocation = rpar.call_method1(
+    "allocate",
+    (
+      prices,
+      weights.weights.clone(),
+      request.amount,
+      request.lot_sizes.clone(),
+      request.fractional.unwrap_or(false),
+    ),
+  )?;
+  let allocation: Allocation = try_calc!(from_python(py, allocation)?);
+
+  let comparisons = if benchmarks.is_empty() {
+    Vec::new()
+  } else {
+    let performance = import_module(py, "performance")?;
+    let values = performance.call_method1("fixed_mix_values", (prices, weights.weights.clone()))?;
+    let comparisons = try_calc!(value_error_as_calc(
+      py,
+      performance.call_method1("compare_with_benchmarks", (values, benchmarks))
+    )?);
+    try_calc!(from_python(py, comparisons)?)
+  };
+
+  Ok(Ok(core::AllocationResponse {
+    weights,
+    positions: allocation.positions,
+    invested: allocation.invested,
+    leftover_cash: allocation.leftover_cash,
+    tracking_error: allocation.tracking_error,
+    benchmarks: comparisons,
+  }))
+}
+
+/// Cost model as the dict `rpar.trade_costs` expects, failing on negative costs
+fn cost_model<'p>(
+  py: Python<'p>,
+  costs: &Option<core::CostModel>,
+) -> PyResult<anyhow::Result<Option<&'p PyDict>>> {
+  let costs = match costs {
+    Some(costs) => costs,
+    None => return Ok(Ok(None)),
+  };
+  let fixed_fee = costs.fixed_fee.unwrap_or(0.0);
+  let commission_bps = costs.commission_bps.unwrap_or(0.0);
+  let slippage_bps = costs.slippage_bps.clone().unwrap_or_default();
+  if fixed_fee < 0.0 || commission_bps < 0.0 || slippage_bps.values().any(|bps| *bps < 0.0) {
+    return Ok(Err(anyhow!("costs can't be negative")));
+  }
+
+  let model = PyDict::new(py);
+  model.set_item("fixed_fee", fixed_fee)?;
+  model.set_item("commission_bps", commission_bps)?;
+  model.set_item("slippage_bps", slippage_bps)?;
+  model.set_item(
+    "asset_classes",
+    costs.asset_classes.clone().unwrap_or_default(),
+  )?;
+  Ok(Ok(Some(model)))
+}
+
+#[derive(Deserialize)]
+struct RebalancePlan {
+  trades: Vec<core::Trade>,
+  portfolio_value: f64,
+  turnover: f64,
+  total_cost: f64,
+  remaining_cash: f64,
+  tracking_error: f64,
+}
+
+/// Trades from holdings to the target weights at the last prices of the weights window
+pub fn rebalance(
+  py: Python,
+  request: &core::RebalanceRequest,
+) -> PyResult<anyhow::Result<core::RebalanceResponse>> {
+  let cash = request.cash.unwrap_or(0.0);
+  if cash < 0.0 || request.holdings.values().any(|quantity| *quantity < 0.0) {
+    return Ok(Err(anyhow!("holdings and cash can't be negative")));
+  }
+  try_calc!(check_lot_sizes(&request.lot_sizes));
+  let costs = try_calc!(cost_model(py, &request.costs)?);
+
+  let rpar = import_rpar(py)?;
+  let (prices, weights) = try_calc!(weights_with_prices(py, rpar, &request.weights)?);
+  try_calc!(check_unleveraged(&weights));
+  let plan = try_calc!(value_error_as_calc(
+    py,
+    rpar.call_method1(
+      "rebalance",
+      (
+        prices,
+        weights.weights.clone(),
+        request.holdings.clone(),
+        cash,
+        request.lot_sizes.clone(),
+        request.fractional.unwrap_or(false),
+        costs,
+      ),
+    )
+  )?);
+  let plan: RebalancePlan = try_calc!(from_python(py, plan)?);
+
+  Ok(Ok(core::RebalanceResponse {
+    weights,
+    trades: plan.trades,
+    portfolio_value: plan.portfolio_value,
+    turnover: plan.turnover,
+    total_cost: plan.total_cost,
+    remaining_cash: plan.remaining_cash,
+    tracking_error: plan.tracking_error,
+  }))
+}
+
+/// Weights calculated on a rolling window at each step of the requested window
+pub fn weights_history(
+  py: Python,
+  request: &core::WeightsHistoryRequest,
+) -> PyResult<anyhow::Result<core::WeightsHistoryResponse>> {
+  try_calc!(check_weights_request(&request.weights));
+  let window_days = request.window_days.unwrap_or(365);
+  if window_days == 0 {
+    return Ok(Err(anyhow!("window days must be positive")));
+  }
+
+  let (start_date, end_date) =
+    date_range(py, request.weights.start_date, request.weights.end_date)?;
+  let backtest = import_module(py, "backtest")?;
+  let history = try_calc!(value_error_as_calc(
+    py,
+    backtest.call_method(
+      "get_weights_history",
+      (
+        request.weights.tickers.clone(),
+        start_date,
+        end_date,
+        window_days,
+        request.step.unwrap_or_default().as_str(),
+      ),
+      Some(weights_options(py, &request.weights)?),
+    )
+  )?);
+  from_python(py, history)
+}
+
+/// Replays the portfolio over its window recalculating weights on the rebalancing schedule
+pub fn backtest(
+  py: Python,
+  request: &core::BacktestRequest,
+) -> PyResult<anyhow::Result<core::BacktestResponse>> {
+  try_calc!(check_weights_request(&request.weights));
+  let lookback_days = request.lookback_days.unwrap_or(365);
+  let initial_value = request.initial_value.unwrap_or(1.0);
+  if lookback_days == 0 || initial_value <= 0.0 {
+    return Ok(Err(anyhow!(
+      "lookback days and initial value must be positive"
+    )));
+  }
+  let (schedule, drift_threshold) = match request.rebalancing.unwrap_or_default() {
+    core::RebalanceSchedule::Monthly => ("monthly", None),
+    core::RebalanceSchedule::Quarterly => ("quarterly", None),
+    core::RebalanceSchedule::Drift { threshold } if threshold > 0.0 => ("drift", Some(threshold)),
+    core::RebalanceSchedule::Drift { .. } => {
+      return Ok(Err(anyhow!("drift threshold must be positive")))
+    }
+  };
+  let costs = try_calc!(cost_model(py, &request.costs)?);
+  let benchmarks = try_calc!(benchmark_mixes(&request.benchmarks));
+
+  let (start_date, end_date) =
+    date_range(py, request.weights.start_date, request.weights.end_date)?;
+  let options = weights_options(py, &request.weights)?;
+  options.set_item("drift_threshold", drift_threshold)?;
+  options.set_item("initial_value", initial_value)?;
+  options.set_item("risk_free_rate", request.risk_free_rate.unwrap_or(0.0))?;
+  options.set_item("cost_model", costs)?;
+  options.set_item("benchmarks", benchmarks)?;
+
+  let backtest = import_module(py, "backtest")?;
+  let result = try_calc!(value_error_as_calc(
+    py,
+    backtest.call_method(
+      "run_backtest",
+      (
+        request.weights.tickers.clone(),
+        start_date,
+        end_date,
+        lookback_days,
+        schedule,
+      ),
+      Some(options),
+    )
+  )?);
+  from_python(py, result)
+}
+
+/// Given weights must have one weight per ticker
+fn check_portfolio_weights(request: &core::WeightsRequest, weights: &[f64]) -> anyhow::Result<()> {
+  if weights.len() != request.tickers.len() {
+    return Err(anyhow!(
+      "{} weights for {} tickers",
+      weights.len(),
+      request.tickers.len()
+    ));
+  }
+  Ok(())
+}
+
+/// Given weights or else the ones calculated for the window, prices are only loaded for the latter
+fn portfolio_weights(
+  py: Python,
+  rpar: &PyModule,
+  request: &core::WeightsRequest,
+  portfolio_weights: &Option<Vec<f64>>,
+) -> PyResult<anyhow::Result<Vec<f64>>> {
+  Ok(Ok(match portfolio_weights {
+    Some(weights) => {
+      try_calc!(check_portfolio_weights(request, weights));
+      weights.clone()
+    }
+    None => try_calc!(weights_with_prices(py, rpar, request)?).1.weights,
+  }))
+}
+
+/// Prices of the window with the given weights, or weights calculated over it when missing
+fn portfolio_with_prices<'p>(
+  py: Python<'p>,
+  rpar: &'p PyModule,
+  request: &core::WeightsRequest,
+  portfolio_weights: &Option<Vec<f64>>,
+) -> PyResult<anyhow::Result<(&'p PyAny, Vec<f64>)>> {
+  Ok(Ok(match portfolio_weights {
+    Some(weights) => {
+      try_calc!(check_portfolio_weights(request, weights));
+      let prices = try_calc!(load_prices(
+        py,
+        rpar,
+        &request.tickers,
+        request.start_date,
+        request.end_date,
+      )?);
+      (prices, weights.clone())
+    }
+    None => {
+      let (prices, weights) = try_calc!(weights_with_prices(py, rpar, request)?);
+      (prices, weights.weights)
+    }
+  }))
+}
+
+/// Risk statistics of given weights or of the ones calculated for the window
+pub fn calc_risk(
+  py: Python,
+  request: &core::RiskRequest,
+) -> PyResult<anyhow::Result<core::RiskResponse>> {
+  let confidence = request.confidence.unwrap_or(0.95);
+  if !(confidence > 0.0 && confidence < 1.0) {
+    return Ok(Err(anyhow!("confidence must be between 0 and 1")));
+  }
+
+  let rpar = import_rpar(py)?;
+  let (prices, weights) = try_calc!(portfolio_with_prices(
+    py,
+    rpar,
+    &request.weights,
+    &request.portfolio_weights
+  )?);
+  let dates = try_calc!(price_dates(prices)?);
+  if dates.is_empty() {
+    return Ok(Err(anyhow!("no prices in the window")));
+  }
+
+  let performance = import_module(py, "performance")?;
+  let stats = try_calc!(value_error_as_calc(
+    py,
+    performance.call_method1("risk_stats", (prices, weights.clone(), confidence))
+  )?);
+
+  Ok(Ok(core::RiskResponse {
+    tickers: request.weights.tickers.clone(),
+    weights,
+    start_date: dates[0],
+    end_date: dates[dates.len() - 1],
+    confidence,
+    stats: try_calc!(from_python(py, stats)?),
+  }))
+}
+
+#[derive(Deserialize)]
+struct CovarianceMatrices {
+  volatilities: Vec<f64>,
+  covariances: Vec<Vec<f64>>,
+  correlations: Vec<Vec<f64>>,
+  covariance_repair: Option<core::CovarianceRepair>,
+}
+
+pub fn calc_covariance(
+  py: Python,
+  query: &core::CovarianceQuery,
+) -> PyResult<anyhow::Result<core::CovarianceResponse>> {
+  let rpar = import_rpar(py)?;
+  let prices = try_calc!(load_prices(
+    py,
+    rpar,
+    &query.tickers,
+    query.start_date,
+    query.end_date
+  )?);
+  let dates = try_calc!(price_dates(prices)?);
+  if dates.len() < 2 {
+    return Ok(Err(anyhow!("not enough prices in the window")));
+  }
+
+  let matrices = try_calc!(value_error_as_calc(
+    py,
+    rpar.call_method1("get_covariance_matrices", (prices,))
+  )?);
+  let matrices: CovarianceMatrices = try_calc!(from_python(py, matrices)?);
+
+  Ok(Ok(core::CovarianceResponse {
+    tickers: query.tickers.clone(),
+    start_date: dates[0],
+    end_date: dates[dates.len() - 1],
+    volatilities: matrices.volatilities,
+    covariances: matrices.covariances,
+    correlations: matrices.correlations,
+    covariance_repair: matrices.covariance_repair,
+  }))
+}
+
+fn check_views(tickers: &[String], views: &[core::View]) -> anyhow::Result<()> {
+  for view in views {
+    for ticker in std::iter::once(&view.ticker).chain(&view.relative_to) {
+      if !tickers.contains(ticker) {
+        return Err(anyhow!("view on ticker {} which isn't requested", ticker));
+      }
+    }
+    if view.relative_to.as_ref() == Some(&view.ticker) {
+      return Err(anyhow!("view on {} is relative to itself", view.ticker));
+    }
+    if !(view.confidence > 0.0 && view.confidence <= 1.0) {
+      return Err(anyhow!(
+        "confidence of view on {} must be above 0 and at most 1",
+        view.ticker
+      ));
+    }
+  }
+  Ok(())
+}
+
+#[derive(Deserialize)]
+struct BlackLittermanReturns {
+  equilibrium_returns: Vec<f64>,
+  posterior_returns: Vec<f64>,
+  mean_variance_weights: Vec<f64>,
+}
+
+pub fn black_litterman(
+  py: Python,
+  request: &core::BlackLittermanRequest,
+) -> PyResult<anyhow::Result<core::BlackLittermanResponse>> {
+  try_calc!(check_views(&request.weights.tickers, &request.views));
+  let risk_aversion = request.risk_aversion.unwrap_or(2.5);
+  let tau = request.tau.unwrap_or(0.05);
+  if risk_aversion <= 0.0 || tau <= 0.0 {
+    return Ok(Err(anyhow!("risk aversion and tau must be positive")));
+  }
+
+  let rpar = import_rpar(py)?;
+  let (prices, weights) = try_calc!(weights_with_prices(py, rpar, &request.weights)?);
+  let views: Vec<(String, Option<String>, f64, f64)> = request
+    .views
+    .iter()
+    .map(|view| {
+      (
+        view.ticker.clone(),
+        view.relative_to.clone(),
+        view.expected_return,
+        view.confidence,
+      )
+    })
+    .collect();
+
+  let black_litterman = import_module(py, "black_litterman")?;
+  let returns = try_calc!(value_error_as_calc(
+    py,
+    black_litterman.call_method1(
+      "black_litterman",
+      (prices, weights.weights.clone(), views, risk_aversion, tau),
+    )
+  )?);
+  let returns: BlackLittermanReturns = try_calc!(from_python(py, returns)?);
+
+  Ok(Ok(core::BlackLittermanResponse {
+    weights,
+    equilibrium_returns: returns.equilibrium_returns,
+    posterior_returns: returns.posterior_returns,
+    mean_variance_weights: returns.mean_variance_weights,
+    risk_aversion,
+    tau,
+  }))
+}
+
+#[derive(Deserialize)]
+struct Frontier {
+  expected_returns: Vec<f64>,
+  points: Vec<core::FrontierPoint>,
+  portfolio: core::FrontierPosition,
+}
+
+pub fn frontier(
+  py: Python,
+  request: &core::FrontierRequest,
+) -> PyResult<anyhow::Result<core::FrontierResponse>> {
+  if let Some(expected_returns) = &request.expected_returns {
+    if expected_returns.len() != request.weights.tickers.len() {
+      return Ok(Err(anyhow!(
+        "{} expected returns for {} tickers",
+        expected_returns.len(),
+        request.weights.tickers.len()
+      )));
+    }
+  }
+  let points = request.points.unwrap_or(20);
+  if !(2..=200).contains(&points) {
+    return Ok(Err(anyhow!("frontier needs between 2 and 200 points")));
+  }
+
+  let rpar = import_rpar(py)?;
+  let (prices, weights) = try_calc!(weights_with_prices(py, rpar, &request.weights)?);
+
+  let frontier = import_module(py, "frontier")?;
+  let result = try_calc!(value_error_as_calc(
+    py,
+    frontier.call_method1(
+      "efficient_frontier",
+      (
+        prices,
+        weights.weights.clone(),
+        request.expected_returns.clone(),
+        points,
+      ),
+    )
+  )?);
+  let result: Frontier = try_calc!(from_python(py, result)?);
+
+  Ok(Ok(core::FrontierResponse {
+    weights,
+    expected_returns: result.expected_returns,
+    points: result.points,
+    portfolio: result.portfolio,
+  }))
+}
+
+#[derive(Deserialize)]
+struct Simulation {
+  times: Vec<f64>,
+  percentiles: Vec<core::PercentilePath>,
+  target_probability: Option<f64>,
+  depletion_probability: f64,
+}
+
+pub fn simulate(
+  py: Python,
+  request: &core::SimulationRequest,
+) -> PyResult<anyhow::Result<core::SimulationResponse>> {
+  if !(request.horizon_years > 0.0 && request.horizon_years <= 100.0) {
+    return Ok(Err(anyhow!(
+      "horizon must be above 0 and at most 100 years"
+    )));
+  }
+  let paths = request.paths.unwrap_or(1000);
+  if !(1..=100_000).contains(&paths) {
+    return Ok(Err(anyhow!("between 1 and 100000 paths can be simulated")));
+  }
+  if request.block_days == Some(0) {
+    return Ok(Err(anyhow!("bootstrap blocks must be at least a day")));
+  }
+  let percentiles = request
+    .percentiles
+    .clone()
+    .unwrap_or_else(|| vec![5.0, 25.0, 50.0, 75.0, 95.0]);
+  if percentiles
+    .iter()
+    .any(|percentile| !(0.0..=100.0).contains(percentile))
+  {
+    return Ok(Err(anyhow!("percentiles must be between 0 and 100")));
+  }
+  let seed = request.seed.unwrap_or(0);
+
+  let rpar = import_rpar(py)?;
+  let (prices, weights) = try_calc!(portfolio_with_prices(
+    py,
+    rpar,
+    &request.weights,
+    &request.portfolio_weights
+  )?);
+  let dates = try_calc!(price_dates(prices)?);
+  if dates.is_empty() {
+    return Ok(Err(anyhow!("no prices in the window")));
+  }
+
+  let options = PyDict::new(py);
+  options.set_item("paths", paths)?;
+  options.set_item("model", request.model.unwrap_or_default().as_str())?;
+  options.set_item("block_days", request.block_days.unwrap_or(20))?;
+  options.set_item("initial_value", request.initial_value.unwrap_or(1.0))?;
+  options.set_item(
+    "monthly_cash_flow",
+    request.monthly_cash_flow.unwrap_or(0.0),
+  )?;
+  options.set_item("target_value", request.target_value)?;
+  options.set_item("percentiles", percentiles)?;
+  options.set_item("seed", seed)?;
+
+  let simulation = import_module(py, "simulation")?;
+  let result = try_calc!(value_error_as_calc(
+    py,
+    simulation.call_method(
+      "simulate",
+      (prices, weights.clone(), request.horizon_years),
+      Some(options),
+    )
+  )?);
+  let result: Simulation = try_calc!(from_python(py, result)?);
+
+  Ok(Ok(core::SimulationResponse {
+    tickers: request.weights.tickers.clone(),
+    weights,
+    start_date: dates[0],
+    end_date: dates[dates.len() - 1],
+    times: result.times,
+    percentiles: result.percentiles,
+    target_probability: result.target_probability,
+    depletion_probability: result.depletion_probability,
+    seed,
+  }))
+}
+
+pub fn stress_test(
+  py: Python,
+  request: &core::StressTestRequest,
+) -> PyResult<anyhow::Result<core::StressTestResponse>> {
+  if request.scenarios.is_empty() {
+    return Ok(Err(anyhow!("no stress scenarios to replay")));
+  }
+  let today = chrono::Utc::now().naive_utc().date();
+  let mut scenarios = Vec::new();
+  for scenario in &request.scenarios {
+    let (start_date, end_date) = scenario.dates();
+    if start_date >= end_date || end_date > today {
+      return Ok(Err(anyhow!(
+        "stress period {} must start before it ends and end by today",
+        scenario.name()
+      )));
+    }
+    scenarios.push((
+      scenario.name().to_string(),
+      py_date(py, start_date)?,
+      py_date(py, end_date)?,
+    ));
+  }
+
+  let rpar = import_rpar(py)?;
+  let weights = try_calc!(portfolio_weights(
+    py,
+    rpar,
+    &request.weights,
+    &request.portfolio_weights
+  )?);
+
+  let stress = import_module(py, "stress")?;
+  let results = try_calc!(value_error_as_calc(
+    py,
+    stress.call_method1(
+      "stress_test",
+      (
+        request.weights.tickers.clone(),
+        weights.clone(),
+        scenarios,
+        request.proxies.clone(),
+      ),
+    )
+  )?);
+
+  Ok(Ok(core::StressTestResponse {
+    tickers: request.weights.tickers.clone(),
+    weights,
+    scenarios: try_calc!(from_python(py, results)?),
+  }))
+}
+
+fn price_dates(prices: &PyAny) -> PyResult<anyhow::Result<Vec<NaiveDate>>> {
+  let dates: Vec<String> = prices
+    .getattr("index")?
+    .call_method1("strftime", ("%Y-%m-%d",))?
+    .call_method0("tolist")?
+    .extract()?;
+  Ok(
+    dates
+      .iter()
+      .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|err| anyhow!("can't parse price dates: {}", err)),
+  )
+}
+
+pub fn load_prices_table(
+  py: Python,
+  query: &core::PricesQuery,
+) -> PyResult<anyhow::Result<core::PricesResponse>> {
+  let rpar = import_rpar(py)?;
+  let prices = try_calc!(load_prices(
+    py,
+    rpar,
+    &query.tickers,
+    query.start_date,
+    query.end_date
+  )?);
+  let dates = try_calc!(price_dates(prices)?);
+  let values: Vec<Vec<f64>> = prices
+    .getattr("values")?
+    .call_method0("tolist")?
+    .extract()?;
+
+  Ok(Ok(core::PricesResponse {
+    tickers: query.tickers.clone(),
+    dates,
+    prices: values,
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn weights_request(tickers: &[&str]) -> core::WeightsRequest {
+    core::WeightsRequest {
+      tickers: tickers.iter().map(|ticker| ticker.to_string()).collect(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn it_rejects_invalid_risk_budgets() {
+    for risk_budget in &[
+      vec![0.5, -0.5],
+      vec![0.0, 0.0],
+      vec![f64::NAN, 1.0],
+      vec![f64::INFINITY, 1.0],
+      vec![1.0],
+    ] {
+      let request = core::WeightsRequest {
+        risk_budget: Some(risk_budget.clone()),
+        ..weights_request(&["SPY", "TLT"])
+      };
+      assert!(
+        check_weights_request(&request).is_err(),
+        "{:?} passed",
+        risk_budget
+      );
+    }
+  }
+
+  fn group(
+    name: &str,
+    tickers: &[&str],
+    budget: f64,
+    sub_budget: Option<Vec<f64>>,
+  ) -> core::RiskBudgetGroup {
+    core::RiskBudgetGroup {
+      name: name.to_string(),
+      tickers: tickers.iter().map(|ticker| ticker.to_string()).collect(),
+      budget,
+      sub_budget,
+    }
+  }
+
+  fn grouped_request(groups: Vec<core::RiskBudgetGroup>) -> core::WeightsRequest {
+    core::WeightsRequest {
+      risk_budget_groups: Some(groups),
+      ..weights_request(&["SPY", "EFA", "TLT"])
+    }
+  }
+
+  #[test]
+  fn it_splits_group_budgets_equally() {
+    let request = grouped_request(vec![
+      group("stocks", &["SPY", "EFA"], 0.6, None),
+      group("bonds", &["TLT"], 0.4, None),
+    ]);
+    assert_eq!(
+      asset_risk_budget(&request).unwrap(),
+      Some(vec![0.3, 0.3, 0.4])
+    );
+  }
+
+  #[test]
+  fn it_splits_group_budgets_by_sub_budget() {
+    let request = grouped_request(vec![
+      group("stocks", &["EFA", "SPY"], 0.5, Some(vec![1.0, 3.0])),
+      group("bonds", &["TLT"], 0.5, None),
+    ]);
+    assert_eq!(
+      asset_risk_budget(&request).unwrap(),
+      Some(vec![0.375, 0.125, 0.5])
+    );
+  }
+
+  #[test]
+  fn it_rejects_invalid_groups() {
+    for groups in &[
+      vec![
+        group("stocks", &["SPY", "EFA"], 0.6, None),
+        group("bonds", &["TLT", "SPY"], 0.4, None),
+      ],
+      vec![
+        group("stocks", &["SPY", "EFA"], 0.6, None),
+        group("bonds", &["IEF"], 0.4, None),
+      ],
+      vec![group("stocks", &["SPY", "EFA"], 0.6, None)],
+      vec![
+        group("stocks", &["SPY", "EFA"], 0.6, None),
+        group("bonds", &["TLT"], 0.0, None),
+      ],
+      vec![
+        group("stocks", &["SPY", "EFA"], 0.6, Some(vec![1.0, 0.0])),
+        group("bonds", &["TLT"], 0.4, None),
+      ],
+      vec![
+        group("stocks", &["SPY", "EFA"], 0.6, Some(vec![1.0])),
+        group("bonds", &["TLT"], 0.4, None),
+      ],
+    ] {
+      let request = grouped_request(groups.clone());
+      assert!(asset_risk_budget(&request).is_err(), "{:?} passed", groups);
+    }
+  }
+
+  #[test]
+  fn it_signs_long_short_by_sides_or_views() {
+    let long_short = |sides, views| core::WeightsRequest {
+      long_short: Some(core::LongShort {
+        sides,
+        views,
+        ..Default::default()
+      }),
+      ..weights_request(&["SPY", "TLT"])
+    };
+    assert_eq!(long_short_signs(&weights_request(&["SPY"])).unwrap(), None);
+    assert_eq!(
+      long_short_signs(&long_short(
+        Some(vec![core::PositionSide::Short, core::PositionSide::Long]),
+        Some(vec![1.0, -1.0])
+      ))
+      .unwrap(),
+      Some(vec![-1.0, 1.0])
+    );
+    assert_eq!(
+      long_short_signs(&long_short(None, Some(vec![0.02, -0.01]))).unwrap(),
+      Some(vec![1.0, -1.0])
+    );
+    assert!(long_short_signs(&long_short(None, Some(vec![0.02, 0.0]))).is_err());
+    assert!(long_short_signs(&long_short(None, Some(vec![0.02]))).is_err());
+    assert!(long_short_signs(&long_short(None, None)).is_err());
+  }
+
+  #[test]
+  fn it_checks_views() {
+    let tickers = vec!["SPY".to_string(), "TLT".to_string()];
+    let view = |ticker: &str, relative_to: Option<&str>, confidence| core::View {
+      ticker: ticker.to_string(),
+      relative_to: relative_to.map(str::to_string),
+      expected_return: 0.05,
+      confidence,
+    };
+    assert!(check_views(&tickers, &[view("SPY", Some("TLT"), 1.0)]).is_ok());
+    assert!(check_views(&tickers, &[view("EFA", None, 0.5)]).is_err());
+    assert!(check_views(&tickers, &[view("SPY", Some("EFA"), 0.5)]).is_err());
+    assert!(check_views(&tickers, &[view("SPY", Some("SPY"), 0.5)]).is_err());
+    assert!(check_views(&tickers, &[view("SPY", None, 0.0)]).is_err());
+    assert!(check_views(&tickers, &[view("SPY", None, 1.5)]).is_err());
+  }
+
+  #[test]
+  fn it_names_benchmark_mixes() {
+    let benchmark = |name: Option<&str>, tickers: &[&str]| core::Benchmark {
+      name: name.map(str::to_string),
+      weights: tickers
+        .iter()
+        .map(|ticker| (ticker.to_string(), 0.5))
+        .collect(),
+    };
+    assert!(benchmark_mixes(&None).unwrap().is_empty());
+    let mixes = benchmark_mixes(&Some(vec![
+      benchmark(Some("60/40"), &["SPY", "TLT"]),
+      benchmark(None, &["TLT", "SPY"]),
+    ]))
+    .unwrap();
+    let names: Vec<&str> = mixes.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["60/40", "SPY/TLT"]);
+    assert!(benchmark_mixes(&Some(vec![benchmark(None, &[])])).is_err());
+  }
+
+  #[test]
+  fn it_measures_turnover_on_unit_gross_exposures() {
+    assert!((turnover(&[0.5, 0.5], &[1.0, 3.0]) - 0.5).abs() < 1e-12);
+    assert!((turnover(&[0.5, -0.5], &[2.0, -2.0])).abs() < 1e-12);
+    assert!(turnover(&[1.0, -1.0], &[1.0, -1.0]).abs() < 1e-12);
+    assert!(turnover(&[0.3, 0.3], &[0.5, 0.5]).abs() < 1e-12);
+    assert!((turnover(&[0.2, 0.4], &[0.5, 0.5]) - 1.0 / 3.0).abs() < 1e-12);
+  }
+
+  #[test]
+  fn it_rejects_max_leverage_below_gross_exposure() {
+    let request = core::WeightsRequest {
+      max_leverage: Some(1.0),
+      long_short: Some(core::LongShort {
+        sides: Some(vec![core::PositionSide::Long, core::PositionSide::Short]),
+        gross_exposure: Some(2.0),
+        ..Default::default()
+      }),
+      ..weights_request(&["SPY", "TLT"])
+    };
+    assert!(check_weights_request(&request).is_err());
+    let request = core::WeightsRequest {
+      max_leverage: None,
+      ..request
+    };
+    assert!(check_weights_request(&request).is_ok());
+  }
+
+  #[test]
+  fn it_accepts_risk_budgets_with_zero_shares() {
+    let request = core::WeightsRequest {
+      risk_budget: Some(vec![0.0, 2.0]),
+      ..weights_request(&["SPY", "TLT"])
+    };
+    assert!(check_weights_request(&request).is_ok());
+  }
+}
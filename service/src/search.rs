@@ -0,0 +1,32 @@
+use anyhow::Context;
+
+pub async fn find_tickers(query: &core::SearchQuery) -> anyhow::Result<serde_json::Value> {
+  let url = format!("https://finance.yahoo.com/_finance_doubledown/api/resource/searchassist;searchTerm={}?device=console&returnMeta=true", &query.term);
+  let mut json: serde_json::Value = reqwest::get(&url)
+    .await
+    .with_context(|| format!("Can't fetch URL {}", &url))?
+    .json()
+    .await
+    .with_context(|| "Can't parse json")?;
+  // will return Value::Null null if not found in json
+  let items = json["data"]["items"].take();
+  Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use anyhow::Result;
+
+  #[ignore]
+  #[actix_rt::test]
+  async fn it_can_find_tickers() -> Result<()> {
+    let tickers = find_tickers(&core::SearchQuery {
+      term: "spy".to_string(),
+    })
+    .await?;
+    println!("Response: {}", serde_json::to_string(&tickers)?);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,108 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+import numpy as np
+
+import rpar
+
+
+# Simulated days over all paths, beyond that a simulation takes too long
+MAX_PATH_DAYS = 100_000_000
+
+# Paths simulated at once within a month, bounding the memory of daily returns
+CHUNK_PATHS = 10_000
+
+
+def _bootstrap_indices(observations, paths, start, end, block_days, rng,
+                       block_starts=None):
+
+    # Consecutive days from random starting points keep the autocorrelation
+    # and volatility clustering of returns within blocks. Blocks are drawn as
+    # days from start to end come, block starts of the block still running at
+    # start are carried over, the ones of the block running at end returned
+    first_block = start // block_days
+    last_block = (end - 1) // block_days
+    carried = start % block_days != 0
+    starts = rng.integers(0, observations - block_days + 1,
+                          size=(paths, last_block - first_block + 1 - carried))
+    if carried:
+        starts = np.column_stack([block_starts, starts])
+    days = np.arange(start, end)
+    return (starts[:, days // block_days - first_block] + days % block_days,
+            starts[:, -1])
+
+
+def simulate(prices, weights, horizon_years, paths=1000,
+             model='multivariate_normal', block_days=20, initial_value=1.0,
+             monthly_cash_flow=0.0, target_value=None,
+             percentiles=(5, 25, 50, 75, 95), seed=0):
+
+    # Portfolio rebalanced to its weights daily, the rest up to 1 is cash
+    # earning nothing, cash flows are added (or withdrawn when negative) at
+    # the end of each month
+    weights = np.asarray(weights, dtype=float)
+    log_changes = (np.log(prices) - np.log(prices.shift(1))).iloc[1:, :].values
+    if len(log_changes) < 2:
+        raise ValueError('not enough prices in the window to simulate')
+    steps = int(round(horizon_years * rpar.BUSINESS_DAYS_PER_YEAR))
+    months = int(np.ceil(horizon_years * 12))
+    if steps < 1 or months < 1:
+        raise ValueError('horizon is shorter than a day')
+    if paths * steps > MAX_PATH_DAYS:
+        raise ValueError('{} paths over {} days are more than {} simulated '
+                         'days'.format(paths, steps, MAX_PATH_DAYS))
+    month_ends = np.round(np.arange(1, months + 1) * steps / months).astype(int)
+
+    rng = np.random.default_rng(seed)
+    if model == 'multivariate_normal':
+        means = np.mean(log_changes, axis=0)
+        covariances = np.cov(log_changes, rowvar=False).reshape(
+            len(weights), len(weights))
+    elif model == 'block_bootstrap':
+        if len(log_changes) < block_days:
+            raise ValueError('window of {} returns is shorter than a '
+                             'bootstrap block of {} days'.format(
+                                 len(log_changes), block_days))
+        block_starts = None
+    else:
+        raise ValueError('unknown simulation model {}'.format(model))
+
+    values = np.full(paths, float(initial_value))
+    depleted = np.zeros(paths, dtype=bool)
+    times = [0.0]
+    # Percentiles are taken month by month instead of keeping all values
+    recorded = [np.percentile(values, percentiles)]
+    start = 0
+    for end in month_ends:
+        if end > start:
+            if model == 'block_bootstrap':
+                indices, block_starts = _bootstrap_indices(
+                    len(log_changes), paths, start, end, block_days, rng,
+                    block_starts)
+            for chunk in range(0, paths, CHUNK_PATHS):
+                chunk_paths = slice(chunk, chunk + CHUNK_PATHS)
+                if model == 'multivariate_normal':
+                    month_changes = rng.multivariate_normal(
+                        means, covariances,
+                        size=(len(values[chunk_paths]), end - start))
+                else:
+                    month_changes = log_changes[indices[chunk_paths]]
+                daily_returns = (np.exp(month_changes) - 1) @ weights
+                values[chunk_paths] *= np.prod(1 + daily_returns, axis=1)
+        values = values + monthly_cash_flow
+        depleted |= values <= 0
+        values = np.maximum(values, 0.0)
+        times.append(end / rpar.BUSINESS_DAYS_PER_YEAR)
+        recorded.append(np.percentile(values, percentiles))
+        start = end
+
+    recorded = np.array(recorded)
+    return {'times': times,
+            'percentiles': [
+                {'percentile': float(percentile),
+                 'values': recorded[:, i].tolist()}
+                for i, percentile in enumerate(percentiles)],
+            'target_probability': (
+                None if target_value is None
+                else float(np.mean(values >= target_value))),
+            'depletion_probability': float(np.mean(depleted))}
@@ -0,0 +1,87 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+import datetime
+
+import pandas as pd
+
+import rpar
+
+
+def _relative_prices(prices, period, ticker, proxies):
+
+    # Prices relative to the first day of the period, from the proxy when the
+    # ticker itself has gaps in the period, None when neither covers it
+    for source in (ticker, proxies.get(ticker)):
+        if (source is not None and source in period.columns and
+                not period[source].isnull().any()):
+            relative_prices = prices[source] / period[source].iloc[0]
+            return relative_prices.loc[period.index[0]:], source
+    return None, None
+
+
+def _replay(yahoo_tickers, weights, name, start_date, end_date, proxies):
+
+    # Prices after the period are loaded as well to see when losses recover
+    loaded_tickers = list(dict.fromkeys(
+        list(yahoo_tickers) +
+        [proxies[ticker] for ticker in yahoo_tickers if ticker in proxies]))
+    prices = rpar.get_prices(loaded_tickers, start_date, datetime.date.today())
+    period = prices.loc[pd.Timestamp(start_date):pd.Timestamp(end_date)]
+    if period.empty:
+        raise ValueError('no prices in stress period {}'.format(name))
+
+    # The allocation is held through the period without rebalancing, tickers
+    # without data are flagged and their weight is held as cash
+    values = pd.Series(1.0 - sum(weights),
+                       index=prices.loc[period.index[0]:].index)
+    contributions = []
+    proxied = []
+    missing_tickers = []
+    for ticker, weight in zip(yahoo_tickers, weights):
+        relative_prices, source = _relative_prices(prices, period, ticker,
+                                                   proxies)
+        if relative_prices is None:
+            missing_tickers.append(ticker)
+            values += weight
+            contributions.append(0.0)
+            continue
+        if source != ticker:
+            proxied.append({'ticker': ticker, 'proxy': source})
+        values += weight * relative_prices
+        contributions.append(
+            float(weight * (relative_prices.loc[period.index[-1]] - 1)))
+
+    period_values = values.loc[:period.index[-1]]
+    drawdowns = 1 - period_values / period_values.cummax()
+    trough_date = drawdowns.idxmax()
+    peak_value = period_values.loc[:trough_date].max()
+
+    # Recovered once the value gets back to the peak before the trough
+    recovered = values.loc[trough_date:]
+    recovered = recovered[recovered >= peak_value]
+    recovery_date = None if recovered.empty else recovered.index[0]
+
+    return {'name': name,
+            'start_date': period.index[0].strftime('%Y-%m-%d'),
+            'end_date': period.index[-1].strftime('%Y-%m-%d'),
+            'total_return': float(period_values.iloc[-1] - 1),
+            'max_drawdown': float(drawdowns.max()),
+            'trough_date': trough_date.strftime('%Y-%m-%d'),
+            'recovery_date': (None if recovery_date is None
+                              else recovery_date.strftime('%Y-%m-%d')),
+            'recovery_days': (None if recovery_date is None
+                              else (recovery_date - trough_date).days),
+            'contributions': contributions,
+            'proxies': proxied,
+            'missing_tickers': missing_tickers,
+            'actual_history': not proxied and not missing_tickers}
+
+
+def stress_test(yahoo_tickers, weights, scenarios, proxies=None):
+
+    # Scenarios are (name, start_date, end_date), proxies map tickers to the
+    # ones standing in for them in periods they have no data for
+    return [_replay(yahoo_tickers, weights, name, start_date, end_date,
+                    proxies or {})
+            for name, start_date, end_date in scenarios]
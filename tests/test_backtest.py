@@ -0,0 +1,118 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of backtests and weights history on simulated prices, run them from
+# the project root:
+#
+#   python -m unittest discover tests
+
+import datetime
+import os
+import sys
+import unittest
+from unittest import mock
+
+import numpy as np
+import pandas as pd
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import backtest  # noqa: E402
+import rpar  # noqa: E402
+from test_rpar import random_prices  # noqa: E402
+
+TICKERS = ['T0', 'T1', 'T2']
+
+
+def price_loader(prices):
+
+    # Stands in for rpar.get_prices, which downloads them from Yahoo
+    def get_prices(yahoo_tickers, start_date, end_date):
+        return prices.loc[pd.Timestamp(start_date):pd.Timestamp(end_date),
+                          list(yahoo_tickers)]
+    return get_prices
+
+
+class BacktestTest(unittest.TestCase):
+
+    def setUp(self):
+        self.prices = random_prices(3, np.random.default_rng(9))
+
+    def run_backtest(self, **options):
+        with mock.patch.object(rpar, 'get_prices',
+                               price_loader(self.prices)):
+            return backtest.run_backtest(TICKERS, datetime.date(2020, 7, 1),
+                                         datetime.date(2021, 1, 29), 120,
+                                         'monthly', **options)
+
+    def test_monthly_rebalances(self):
+        result = self.run_backtest()
+        months = sorted({date[:7] for date in result['dates']})
+        self.assertEqual([rebalance['date'][:7]
+                          for rebalance in result['rebalances']], months)
+        self.assertEqual(result['values'][0], 1.0)
+        self.assertEqual(result['total_cost'], 0.0)
+        for rebalance in result['rebalances']:
+            self.assertAlmostEqual(sum(rebalance['weights']), 1.0)
+            self.assertIsInstance(rebalance['converged'], bool)
+
+    def test_costs_are_paid_from_the_portfolio(self):
+        cost_model = {'fixed_fee': 0.001, 'commission_bps': 10.0,
+                      'slippage_bps': {}, 'asset_classes': {}}
+        result = self.run_backtest(cost_model=cost_model)
+        self.assertGreater(result['total_cost'], 0.0)
+        self.assertLess(result['values'][0], 1.0)
+        self.assertAlmostEqual(sum(rebalance['cost']
+                                   for rebalance in result['rebalances']),
+                               result['total_cost'])
+
+    def test_weights_history(self):
+        with mock.patch.object(rpar, 'get_prices',
+                               price_loader(self.prices)):
+            history = backtest.get_weights_history(
+                TICKERS, datetime.date(2020, 7, 1),
+                datetime.date(2020, 12, 31), 120, 'monthly')
+        self.assertEqual(len(history['dates']), 6)
+        self.assertEqual(len(history['converged']), 6)
+        for weights in history['weights']:
+            self.assertAlmostEqual(sum(weights), 1.0)
+
+
+class RebalancingDayTest(unittest.TestCase):
+
+    def test_schedules(self):
+        def is_rebalancing_day(schedule, previous_date, date):
+            return backtest._is_rebalancing_day(
+                schedule, None, pd.Timestamp(previous_date),
+                pd.Timestamp(date), None, None)
+        self.assertTrue(is_rebalancing_day('monthly', '2020-01-31',
+                                           '2020-02-03'))
+        self.assertFalse(is_rebalancing_day('monthly', '2020-02-03',
+                                            '2020-02-04'))
+        self.assertTrue(is_rebalancing_day('quarterly', '2020-03-31',
+                                           '2020-04-01'))
+        self.assertFalse(is_rebalancing_day('quarterly', '2020-04-30',
+                                            '2020-05-01'))
+        with self.assertRaises(ValueError):
+            is_rebalancing_day('daily', '2020-01-01', '2020-01-02')
+
+    def test_drift(self):
+        target_weights = np.array([0.5, 0.5])
+        self.assertTrue(backtest._is_rebalancing_day(
+            'drift', 0.05, None, None, np.array([0.56, 0.44]),
+            target_weights))
+        self.assertFalse(backtest._is_rebalancing_day(
+            'drift', 0.05, None, None, np.array([0.52, 0.48]),
+            target_weights))
+
+    def test_turnover_limits_need_held_weights(self):
+        options = {'method': 'risk_parity', 'max_turnover': 0.1}
+        self.assertEqual(backtest._anchored_options(options, None),
+                         {'method': 'risk_parity'})
+        self.assertEqual(
+            backtest._anchored_options(options, [0.5, 0.5]),
+            dict(options, previous_weights=[0.5, 0.5]))
+
+
+if __name__ == '__main__':
+    unittest.main()
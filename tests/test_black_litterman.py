@@ -0,0 +1,58 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of Black-Litterman returns on simulated prices, run them from the
+# project root:
+#
+#   python -m unittest discover tests
+
+import os
+import sys
+import unittest
+
+import numpy as np
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import black_litterman  # noqa: E402
+from test_rpar import random_prices  # noqa: E402
+
+WEIGHTS = [0.5, 0.3, 0.2]
+
+
+class BlackLittermanTest(unittest.TestCase):
+
+    def setUp(self):
+        self.prices = random_prices(3, np.random.default_rng(15))
+
+    def test_without_views_the_weights_are_optimal(self):
+        result = black_litterman.black_litterman(self.prices, WEIGHTS, [])
+        self.assertEqual(result['posterior_returns'],
+                         result['equilibrium_returns'])
+        np.testing.assert_allclose(result['mean_variance_weights'], WEIGHTS)
+
+    def test_full_confidence_views_are_met(self):
+        result = black_litterman.black_litterman(
+            self.prices, WEIGHTS, [('T0', None, 0.08, 1.0),
+                                   ('T1', 'T2', 0.02, 1.0)])
+        posterior_returns = result['posterior_returns']
+        self.assertAlmostEqual(posterior_returns[0], 0.08)
+        self.assertAlmostEqual(posterior_returns[1] - posterior_returns[2],
+                               0.02)
+
+    def test_partial_confidence_blends_the_view(self):
+        result = black_litterman.black_litterman(
+            self.prices, WEIGHTS, [('T0', None, 0.3, 0.5)])
+        equilibrium_return = result['equilibrium_returns'][0]
+        self.assertGreater(result['posterior_returns'][0], equilibrium_return)
+        self.assertLess(result['posterior_returns'][0], 0.3)
+
+    def test_redundant_views_are_rejected(self):
+        with self.assertRaises(ValueError):
+            black_litterman.black_litterman(
+                self.prices, WEIGHTS, [('T0', None, 0.08, 1.0),
+                                       ('T0', None, 0.05, 1.0)])
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,62 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of the efficient frontier on simulated prices, run them from the
+# project root:
+#
+#   python -m unittest discover tests
+
+import os
+import sys
+import unittest
+
+import numpy as np
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import frontier  # noqa: E402
+import rpar  # noqa: E402
+from test_rpar import random_prices  # noqa: E402
+
+
+class FrontierTest(unittest.TestCase):
+
+    def setUp(self):
+        self.prices = random_prices(3, np.random.default_rng(16))
+
+    def test_points_trade_volatility_for_return(self):
+        result = frontier.efficient_frontier(
+            self.prices, [1 / 3] * 3, expected_returns=[0.04, 0.07, 0.1],
+            points=5)
+        points = result['points']
+        self.assertEqual(len(points), 5)
+        self.assertAlmostEqual(points[-1]['expected_return'], 0.1, places=6)
+        for point, next_point in zip(points, points[1:]):
+            self.assertLessEqual(point['expected_return'],
+                                 next_point['expected_return'] + 1e-9)
+            self.assertLessEqual(point['volatility'],
+                                 next_point['volatility'] + 1e-6)
+        for point in points:
+            self.assertAlmostEqual(sum(point['weights']), 1.0, places=6)
+            self.assertTrue(min(point['weights']) >= 0.0)
+
+    def test_portfolio_lies_below_the_frontier(self):
+        result = frontier.efficient_frontier(
+            self.prices, [0.2, 0.3, 0.5], expected_returns=[0.04, 0.07, 0.1],
+            points=5)
+        portfolio = result['portfolio']
+        self.assertLessEqual(portfolio['frontier_volatility'],
+                             portfolio['volatility'] + 1e-6)
+        self.assertGreaterEqual(portfolio['frontier_return'],
+                                portfolio['expected_return'] - 1e-6)
+
+    def test_historical_returns_are_annualised_over_business_days(self):
+        # A steady 0.1% a day grows by a business day count of them a year
+        prices = self.prices.copy()
+        prices['T0'] = 100 * np.exp(0.001 * np.arange(len(prices)))
+        self.assertAlmostEqual(frontier._historical_returns(prices)[0],
+                               0.001 * rpar.BUSINESS_DAYS_PER_YEAR)
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,48 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of portfolio risk statistics on simulated prices, run them from the
+# project root:
+#
+#   python -m unittest discover tests
+
+import os
+import sys
+import unittest
+
+import numpy as np
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import performance  # noqa: E402
+import rpar  # noqa: E402
+from test_rpar import random_prices  # noqa: E402
+
+
+class RiskStatsTest(unittest.TestCase):
+
+    def test_tail_losses_and_diversification(self):
+        prices = random_prices(3, np.random.default_rng(10))
+        stats = performance.risk_stats(prices, [0.5, 0.3, 0.2])
+        self.assertGreater(stats['volatility'], 0.0)
+        self.assertGreaterEqual(stats['historical_cvar'],
+                                stats['historical_var'])
+        self.assertGreaterEqual(stats['parametric_cvar'],
+                                stats['parametric_var'])
+        self.assertGreaterEqual(stats['max_drawdown'], 0.0)
+        self.assertLess(stats['max_drawdown'], 1.0)
+        # Long only weights are no more volatile than their assets
+        self.assertGreaterEqual(stats['diversification_ratio'], 1.0 - 1e-9)
+
+    def test_single_asset_is_not_diversified(self):
+        prices = random_prices(2, np.random.default_rng(11))
+        stats = performance.risk_stats(prices, [1.0, 0.0])
+        self.assertAlmostEqual(stats['diversification_ratio'], 1.0)
+        self.assertAlmostEqual(
+            stats['volatility'],
+            prices['T0'].pct_change().std() *
+            np.sqrt(rpar.BUSINESS_DAYS_PER_YEAR))
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,149 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of the risk parity weights on simulated prices, run them from the
+# project root:
+#
+#   python -m unittest discover tests
+
+import os
+import sys
+import unittest
+
+import numpy as np
+import pandas as pd
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import rpar  # noqa: E402
+
+
+def random_prices(size, rng, days=300):
+
+    # Business day prices of assets with different volatilities
+    volatilities = rng.uniform(0.005, 0.02, size)
+    returns = rng.normal(scale=volatilities, size=(days, size))
+    return pd.DataFrame(100 * np.exp(np.cumsum(returns, axis=0)),
+                        index=pd.bdate_range('2020-01-01', periods=days),
+                        columns=['T{}'.format(i) for i in range(size)])
+
+
+class RiskParityTest(unittest.TestCase):
+
+    def test_budgets_are_met(self):
+        prices = random_prices(4, np.random.default_rng(1))
+        for solver in rpar.RISK_PARITY_SOLVERS:
+            weights, diagnostics, _ = rpar.get_weights_with_diagnostics(
+                prices, [0.4, 0.3, 0.2, 0.1], solver=solver)
+            self.assertTrue(diagnostics['converged'], solver)
+            self.assertAlmostEqual(weights.sum(), 1.0)
+
+    def test_zero_budget_assets_fall_back_to_slsqp(self):
+        prices = random_prices(4, np.random.default_rng(2))
+        for solver in rpar.RISK_PARITY_SOLVERS:
+            weights, diagnostics, _ = rpar.get_weights_with_diagnostics(
+                prices, [0.5, 0.0, 0.25, 0.25], solver=solver)
+            self.assertEqual(diagnostics['solver'], 'slsqp')
+            self.assertTrue(np.all(np.isfinite(weights.values)), solver)
+            self.assertAlmostEqual(weights.iloc[1], 0.0, places=3)
+
+
+def log_returns(prices):
+
+    return (np.log(prices) - np.log(prices.shift(1))).iloc[1:, :].values
+
+
+class ShortfallRiskParityTest(unittest.TestCase):
+
+    def test_historical_contributions_sum_up_to_the_shortfall(self):
+        returns = log_returns(random_prices(3, np.random.default_rng(12)))
+        weights = np.array([0.5, 0.3, 0.2])
+        tail_size = int(len(returns) * (1 - 0.95))
+        shortfall = -np.mean(np.sort(returns @ weights)[:tail_size])
+        self.assertAlmostEqual(np.sum(rpar._shortfall_contributions(
+            weights, returns, 0.95, 'historical')), shortfall)
+
+    def test_cornish_fisher_contributions_sum_up_to_the_shortfall(self):
+        returns = log_returns(random_prices(3, np.random.default_rng(13)))
+        weights = np.array([0.5, 0.3, 0.2])
+        self.assertAlmostEqual(
+            np.sum(rpar._shortfall_contributions(weights, returns, 0.95,
+                                                 'cornish_fisher')),
+            rpar._cornish_fisher_shortfall(weights, returns, 0.95),
+            places=6)
+
+    def test_weights_are_solved_with_slsqp(self):
+        prices = random_prices(3, np.random.default_rng(14))
+        for estimator in ('historical', 'cornish_fisher'):
+            weights, diagnostics, _ = rpar.get_weights_with_diagnostics(
+                prices, method='expected_shortfall_risk_parity',
+                shortfall_estimator=estimator)
+            self.assertEqual(diagnostics['solver'], 'slsqp')
+            self.assertAlmostEqual(weights.sum(), 1.0)
+            self.assertTrue(np.all(weights.values >= -1e-6), estimator)
+
+
+class AllocationTest(unittest.TestCase):
+
+    def test_fractional_quantities_match_weights(self):
+        prices = random_prices(3, np.random.default_rng(7))
+        allocation = rpar.allocate(prices, [0.5, 0.3, 0.2], 10000.0,
+                                   fractional=True)
+        for position, weight in zip(allocation['positions'], [0.5, 0.3, 0.2]):
+            self.assertAlmostEqual(position['realized_weight'], weight)
+        self.assertAlmostEqual(allocation['leftover_cash'], 0.0)
+
+    def test_whole_lots_stay_within_the_amount(self):
+        prices = random_prices(3, np.random.default_rng(8))
+        allocation = rpar.allocate(prices, [0.5, 0.3, 0.2], 10000.0,
+                                   lot_sizes={'T0': 10})
+        for position in allocation['positions']:
+            lots = position['quantity'] / position['lot_size']
+            self.assertEqual(lots, round(lots))
+            self.assertAlmostEqual(position['value'],
+                                   position['quantity'] * position['price'])
+        self.assertEqual(allocation['positions'][0]['lot_size'], 10.0)
+        self.assertLessEqual(allocation['invested'], 10000.0)
+        self.assertAlmostEqual(allocation['invested'] +
+                               allocation['leftover_cash'], 10000.0)
+
+
+class RebalanceTest(unittest.TestCase):
+
+    def test_fractional_trades_invest_the_cash(self):
+        prices = random_prices(3, np.random.default_rng(5))
+        plan = rpar.rebalance(prices, [0.5, 0.3, 0.2], {'T0': 20, 'T1': 10},
+                              1000.0, fractional=True)
+        latest_prices = prices.iloc[-1]
+        trades = {trade['ticker']: trade for trade in plan['trades']}
+        self.assertAlmostEqual(plan['portfolio_value'],
+                               20 * latest_prices['T0'] +
+                               10 * latest_prices['T1'] + 1000.0)
+        self.assertAlmostEqual(
+            sum(trade['value'] for trade in plan['trades']), 1000.0)
+        self.assertAlmostEqual(trades['T2']['target_quantity'] *
+                               latest_prices['T2'] / plan['portfolio_value'],
+                               0.2)
+        self.assertAlmostEqual(plan['total_cost'], 0.0)
+        self.assertAlmostEqual(plan['remaining_cash'], 0.0)
+        self.assertAlmostEqual(plan['tracking_error'], 0.0)
+
+    def test_costs_are_paid_from_the_portfolio(self):
+        prices = random_prices(3, np.random.default_rng(6))
+        cost_model = {'fixed_fee': 1.0, 'commission_bps': 10.0,
+                      'slippage_bps': {}, 'asset_classes': {}}
+        plan = rpar.rebalance(prices, [0.5, 0.3, 0.2], {'T0': 30}, 2000.0,
+                              cost_model=cost_model)
+        invested = sum(trade['target_quantity'] * trade['price']
+                       for trade in plan['trades'])
+        self.assertGreater(plan['total_cost'], 0.0)
+        self.assertGreaterEqual(plan['remaining_cash'], 0.0)
+        self.assertLessEqual(invested + plan['total_cost'],
+                             plan['portfolio_value'] + 1e-6)
+        for trade in plan['trades']:
+            self.assertEqual(trade['target_quantity'],
+                             round(trade['target_quantity']))
+
+
+if __name__ == '__main__':
+    unittest.main()
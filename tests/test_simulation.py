@@ -0,0 +1,48 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of the Monte Carlo simulation on simulated prices, run them from the
+# project root:
+#
+#   python -m unittest discover tests
+
+import os
+import sys
+import unittest
+
+import numpy as np
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import simulation  # noqa: E402
+from test_rpar import random_prices  # noqa: E402
+
+
+class SimulationTest(unittest.TestCase):
+
+    def test_same_seed_gives_same_percentiles(self):
+        prices = random_prices(3, np.random.default_rng(3))
+        for model in ('multivariate_normal', 'block_bootstrap'):
+            def simulate(seed):
+                return simulation.simulate(
+                    prices, [0.3, 0.3, 0.3], 0.25, paths=200, model=model,
+                    block_days=5, monthly_cash_flow=0.01, seed=seed)
+            result = simulate(7)
+            self.assertEqual(result['percentiles'],
+                             simulate(7)['percentiles'], model)
+            self.assertNotEqual(result['percentiles'],
+                                simulate(8)['percentiles'], model)
+            # A quarter is three months after the start
+            self.assertEqual(len(result['times']), 4)
+            for percentile in result['percentiles']:
+                self.assertEqual(len(percentile['values']), 4)
+                self.assertEqual(percentile['values'][0], 1.0)
+
+    def test_too_many_simulated_days_are_rejected(self):
+        prices = random_prices(2, np.random.default_rng(4))
+        with self.assertRaises(ValueError):
+            simulation.simulate(prices, [0.5, 0.5], 100, paths=100000)
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,75 @@
+#!/usr/bin/env python
+# coding: utf-8
+
+# Checks of stress period replays on simulated prices, run them from the
+# project root:
+#
+#   python -m unittest discover tests
+
+import datetime
+import os
+import sys
+import unittest
+from unittest import mock
+
+import numpy as np
+
+sys.path.insert(0, os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                                '..'))
+import rpar  # noqa: E402
+import stress  # noqa: E402
+from test_backtest import price_loader  # noqa: E402
+from test_rpar import random_prices  # noqa: E402
+
+TICKERS = ['T0', 'T1', 'T2']
+WEIGHTS = [0.5, 0.3, 0.2]
+GAP_PERIOD = ('gap', datetime.date(2020, 2, 3), datetime.date(2020, 3, 31))
+LATER_PERIOD = ('later', datetime.date(2020, 7, 1),
+                datetime.date(2020, 9, 30))
+
+
+class StressTest(unittest.TestCase):
+
+    def setUp(self):
+        # T2 only has prices from the 101st business day on
+        self.prices = random_prices(3, np.random.default_rng(17))
+        self.prices.iloc[:100, 2] = np.nan
+
+    def stress_test(self, scenarios, proxies=None):
+        with mock.patch.object(rpar, 'get_prices',
+                               price_loader(self.prices)):
+            return stress.stress_test(TICKERS, WEIGHTS, scenarios, proxies)
+
+    def test_contributions_sum_up_to_the_return(self):
+        for replay in self.stress_test([GAP_PERIOD, LATER_PERIOD],
+                                       {'T2': 'T1'}):
+            self.assertAlmostEqual(sum(replay['contributions']),
+                                   replay['total_return'])
+            self.assertGreaterEqual(replay['max_drawdown'], 0.0)
+            self.assertLessEqual(replay['start_date'],
+                                 replay['trough_date'])
+            self.assertLessEqual(replay['trough_date'], replay['end_date'])
+
+    def test_proxies_stand_in_for_gaps(self):
+        gap, later = self.stress_test([GAP_PERIOD, LATER_PERIOD],
+                                      {'T2': 'T1'})
+        self.assertEqual(gap['proxies'], [{'ticker': 'T2', 'proxy': 'T1'}])
+        self.assertEqual(gap['missing_tickers'], [])
+        self.assertFalse(gap['actual_history'])
+        self.assertEqual(later['proxies'], [])
+        self.assertTrue(later['actual_history'])
+
+    def test_gaps_without_proxies_are_held_as_cash(self):
+        gap, = self.stress_test([GAP_PERIOD])
+        self.assertEqual(gap['missing_tickers'], ['T2'])
+        self.assertEqual(gap['contributions'][2], 0.0)
+        self.assertFalse(gap['actual_history'])
+
+    def test_periods_without_prices_are_rejected(self):
+        with self.assertRaises(ValueError):
+            self.stress_test([('weekend', datetime.date(2020, 1, 4),
+                               datetime.date(2020, 1, 5))])
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -1,7 +1,7 @@
 use super::ticker_input::Component as TickerInput;
 use crate::services::rpb::{Service as RbpService, TickerInfo};
 use anyhow::Result;
-use core::GetWeightsQuery;
+use core::{ExportFormat, ExportWeightsQuery, GetWeightsQuery};
 use once_cell::sync::Lazy;
 use serde_json::json;
 use yew::services::Task;
@@ -231,11 +231,43 @@ impl Component {
       <>
         <div class="text-gray-500">{"Calculated porfolio weights"}</div>
         { for self.fetched_tickers.iter().zip(self.fetched_weights.iter()).map(render_ticker_weight) }
+        <div class="pt-2">
+          { for ExportFormat::ALL.iter().map(|format| self.render_export_link(*format)) }
+        </div>
       </>
       }
     }
   }
 
+  fn render_export_link(&self, format: ExportFormat) -> Html {
+    let query = ExportWeightsQuery {
+      tickers: self
+        .fetched_tickers
+        .iter()
+        .map(|ticker_info| ticker_info.symbol.to_string())
+        .collect(),
+      names: Some(
+        self
+          .fetched_tickers
+          .iter()
+          .map(|ticker_info| ticker_info.name.to_string())
+          .collect(),
+      ),
+      start_date: None,
+      end_date: None,
+      risk_budget: None,
+      method: None,
+      format,
+    };
+    html! {
+      <a class="mr-3 text-sm text-purple-400 underline hover:text-purple-500"
+        href=self.props.rbp_service.weights_export_url(&query) download="">
+        <i class="fa fa-download" aria-hidden="true"></i>
+        { format!(" {}", format.as_str().to_uppercase()) }
+      </a>
+    }
+  }
+
   fn render_picked_tickers(&self) -> Html {
     let mut children: Vec<VNode> = vec![];
 
@@ -1,8 +1,9 @@
 use anyhow::Result;
-use core::{GetWeightsQuery, SearchQuery};
-use serde::{Deserialize, Serialize};
+use core::{ExportWeightsQuery, GetWeightsQuery, SearchQuery};
 use yew::{services::fetch::FetchTask, Callback};
 
+pub use core::TickerInfo;
+
 #[derive(PartialEq)]
 pub struct Service {
   pub base: String,
@@ -10,15 +11,6 @@ pub struct Service {
 
 impl super::Service for Service {}
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-pub struct TickerInfo {
-  pub symbol: String,
-  pub name: String,
-  pub exch_disp: String,
-  pub type_disp: String,
-}
-
 impl Service {
   pub fn get_weigths(
     &self,
@@ -36,6 +28,15 @@ impl Service {
     super::Service::get(self, &self.prepend_base("search"), Some(&query), callback)
   }
 
+  /// Link to download weights file, the service responds with an attachment
+  pub fn weights_export_url(&self, query: &ExportWeightsQuery) -> String {
+    format!(
+      "{}?{}",
+      self.prepend_base("weights/export"),
+      serde_qs::to_string(query).unwrap()
+    )
+  }
+
   // TODO this can be taken away with a new PathBuilder abstraction
   pub fn prepend_base(&self, sub_path: &str) -> String {
     format!("{}/{}", self.base, sub_path)